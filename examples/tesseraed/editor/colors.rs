@@ -1,4 +1,4 @@
-use sdl2::pixels::Color;
+use tesserae::Color;
 
 const fn rgba(r:u8,g:u8,b:u8,a:u8) -> Color {
     Color { r : r , g : g , b : b , a : a }
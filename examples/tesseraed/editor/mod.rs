@@ -1,16 +1,15 @@
-mod swatch;
 mod widgets;
 mod tile_editor;
 mod locations;
 mod colors;
 
-use tesserae::{Graphic, TileSet,Tile};
+use tesserae::{Graphic, TileSet,Tile,Swatch};
+use tesserae::ansi::{AnsiColors,Sauce};
 use tile_editor::TileEditor;
 use widgets::{TileSetChooser,ColorChooser};
-use swatch::Swatch;
 use std::io::Cursor;
 use std::fs::File;
-use sdl2::pixels::Color;
+use tesserae::Color;
 use sdl2::event::Event;
 use sdl2::keyboard::Keycode;
 use sdl2::render::{Texture,TextureCreator};
@@ -359,7 +358,6 @@ impl <'r> GraphicsEditor<'r> {
                     editor.set_selected(self.tile_set_chooser.selected());
                     editor.main_loop(canvas, event_pump);
                     let tile_set = TileSet::load_file(self.config.tile_set_file_name).unwrap();
-                    self.editor_view.mark_dirty();
                     self.tile_set_chooser.set_selected(editor.selected());
                     *self.tile_set_chooser.tile_set_mut() = tile_set;
                     self.tile_set_chooser.refresh(true);
@@ -405,14 +403,25 @@ pub fn run<'r>(c: Config<'r>) {
     canvas.set_logical_size(1280,800).unwrap();
     let mut event_pump = sdl_context.event_pump().unwrap();
     let texture_creator = canvas.texture_creator();
+    // ANSI art files are loaded and saved using the characters of the chosen tile set.
+    let is_ansi = c.edit_mode.file_name().ends_with(".ans");
+    let sauce = if is_ansi { Sauce::load_file(c.edit_mode.file_name()).ok().flatten() } else { None };
     let graphic = match c.edit_mode {
         EditMode::New(_, w, h) => 
             Graphic::blank(w,h).textured(&texture_creator),
+        EditMode::Open(f) if is_ansi =>
+            Graphic::load_ansi_file(f, &TileSet::load_file(c.tile_set_file_name).unwrap()).unwrap().textured(&texture_creator),
         EditMode::Open(f) =>
             Graphic::load_file_textured(f,&texture_creator).unwrap(),
     };
     let mut editor = GraphicsEditor::create(graphic,&c,&texture_creator);
     editor.refresh_views();
     editor.main_loop(&mut canvas, &mut event_pump);
-    editor.editor_view.save(&mut File::create(c.edit_mode.file_name()).unwrap()).unwrap()
+    let mut f = File::create(c.edit_mode.file_name()).unwrap();
+    if is_ansi {
+        let colors = if sauce.as_ref().is_none_or(|s| s.ice_colors()) { AnsiColors::IceColors } else { AnsiColors::Classic };
+        editor.editor_view.save_ansi(&mut f, editor.tile_set_chooser.tile_set(), colors, &sauce.unwrap_or_default()).unwrap()
+    } else {
+        editor.editor_view.save(&mut f).unwrap()
+    }
 }
@@ -1,5 +1,4 @@
-use tesserae::{Graphic,TileSet,Tile};
-use super::swatch::Swatch;
+use tesserae::{Graphic,TileSet,Tile,Swatch};
 use super::colors;
 use std::ops::IndexMut;
 use sdl2::render::{Texture,TextureCreator};
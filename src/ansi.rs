@@ -0,0 +1,475 @@
+//! Reading and writing ANSI art files (`.ans`), as drawn for BBSes and DOS, along with
+//! the SAUCE metadata record usually appended to them.
+//!
+//! ANSI art is made of code page 437 characters, so it is usually loaded and saved
+//! with the `cga_ascii` tile set, whose tile indices match the character codes.
+
+use std::fs::File;
+use std::io;
+use std::io::{Read, Write};
+use std::path::Path;
+
+use super::{cp437, semigraphics, Color, Graphic, Tile, TileSet};
+
+const fn rgb(r: u8, g: u8, b: u8) -> Color {
+    Color { r, g, b, a: 255 }
+}
+
+/// The 16 colors used by ANSI art, in the order of the SGR color codes
+/// (black, red, green, yellow, blue, magenta, cyan, white, then the bright versions of each).
+/// These are the colors of the standard VGA text mode palette.
+pub const ANSI_PALETTE: [Color; 16] = [
+    rgb(0, 0, 0), rgb(170, 0, 0), rgb(0, 170, 0), rgb(170, 85, 0),
+    rgb(0, 0, 170), rgb(170, 0, 170), rgb(0, 170, 170), rgb(170, 170, 170),
+    rgb(85, 85, 85), rgb(255, 85, 85), rgb(85, 255, 85), rgb(255, 255, 85),
+    rgb(85, 85, 255), rgb(255, 85, 255), rgb(85, 255, 255), rgb(255, 255, 255),
+];
+
+/// The color for an index into the 256 color palette used by xterm-compatible terminals.
+fn xterm_color(index: u8) -> Color {
+    match index {
+        0..=15 => ANSI_PALETTE[index as usize],
+        16..=231 => {
+            let i = index - 16;
+            let level = |v: u8| if v == 0 { 0 } else { 55 + v * 40 };
+            rgb(level(i / 36), level((i / 6) % 6), level(i % 6))
+        }
+        _ => {
+            let v = 8 + (index - 232) * 10;
+            rgb(v, v, v)
+        }
+    }
+}
+
+const SAUCE_SIZE: usize = 128;
+const COMMENT_SIZE: usize = 64;
+/// The most tiles loaded ANSI art may have, so that a file moving the cursor far down
+/// can't make the loader run out of memory.
+const MAX_TILES: u32 = 1 << 22;
+
+fn sauce_string(bytes: &[u8]) -> String {
+    let end = bytes.iter().rposition(|&b| b != b' ' && b != 0).map_or(0, |i| i + 1);
+    bytes[..end].iter().map(|&b| cp437::to_char(b)).collect()
+}
+
+/// The SAUCE metadata record found at the end of most ANSI art files.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Sauce {
+    pub title: String,
+    pub author: String,
+    pub group: String,
+    /// The date the file was made, in the form `CCYYMMDD`.
+    pub date: String,
+    /// The kind of file. 1 is character-based art, such as ANSI.
+    pub data_type: u8,
+    /// The format of the file within its data type. 1 is ANSI.
+    pub file_type: u8,
+    /// For ANSI art, the width of the image in characters.
+    pub width: u16,
+    /// For ANSI art, the height of the image in characters.
+    pub height: u16,
+    /// Rendering flags. Bit 0 enables "iCE colors", meaning that the blink
+    /// attribute gives bright background colors instead of blinking.
+    pub flags: u8,
+    /// The name of the font the art was drawn for, such as `IBM VGA`.
+    pub font: String,
+    pub comments: Vec<String>,
+}
+
+impl Sauce {
+    /// Read the SAUCE record from the end of the given file contents, if there is one.
+    pub fn from_bytes(data: &[u8]) -> Option<Sauce> {
+        if data.len() < SAUCE_SIZE {
+            return None;
+        }
+        let r = &data[data.len() - SAUCE_SIZE..];
+        if &r[0..7] != b"SAUCE00" {
+            return None;
+        }
+        let mut comments = Vec::new();
+        let lines = r[104] as usize;
+        let block = 5 + lines * COMMENT_SIZE;
+        if lines > 0 && data.len() >= SAUCE_SIZE + block {
+            let c = &data[data.len() - SAUCE_SIZE - block..data.len() - SAUCE_SIZE];
+            if &c[0..5] == b"COMNT" {
+                for line in c[5..].chunks(COMMENT_SIZE) {
+                    comments.push(sauce_string(line));
+                }
+            }
+        }
+        Some(Sauce {
+            title: sauce_string(&r[7..42]),
+            author: sauce_string(&r[42..62]),
+            group: sauce_string(&r[62..82]),
+            date: sauce_string(&r[82..90]),
+            data_type: r[94],
+            file_type: r[95],
+            width: u16::from_le_bytes([r[96], r[97]]),
+            height: u16::from_le_bytes([r[98], r[99]]),
+            flags: r[105],
+            font: sauce_string(&r[106..128]),
+            comments,
+        })
+    }
+    /// Read the SAUCE record from the end of the file at the given path, if there is one.
+    pub fn load_file<P: AsRef<Path>>(path: P) -> io::Result<Option<Sauce>> {
+        let mut data = Vec::new();
+        File::open(path)?.read_to_end(&mut data)?;
+        Ok(Sauce::from_bytes(&data))
+    }
+    /// Whether the blink attribute should be shown as a bright background color.
+    pub fn ice_colors(&self) -> bool {
+        self.flags & 1 != 0
+    }
+    /// Write the SAUCE record (preceded by its comment block, if there are any comments)
+    /// to the given output. The `file_size` is the size of the file contents before the record.
+    /// Strings that are too long for their fields are truncated.
+    pub fn write_to<W: Write>(&self, out: &mut W, file_size: u32) -> io::Result<()> {
+        let lines = self.comments.len().min(255);
+        if lines > 0 {
+            out.write_all(b"COMNT")?;
+            for c in &self.comments[..lines] {
+                write_sauce_string(out, c, COMMENT_SIZE, b' ')?;
+            }
+        }
+        out.write_all(b"SAUCE00")?;
+        write_sauce_string(out, &self.title, 35, b' ')?;
+        write_sauce_string(out, &self.author, 20, b' ')?;
+        write_sauce_string(out, &self.group, 20, b' ')?;
+        write_sauce_string(out, &self.date, 8, b' ')?;
+        out.write_all(&file_size.to_le_bytes())?;
+        out.write_all(&[self.data_type, self.file_type])?;
+        out.write_all(&self.width.to_le_bytes())?;
+        out.write_all(&self.height.to_le_bytes())?;
+        out.write_all(&[0, 0, 0, 0, lines as u8, self.flags])?;
+        write_sauce_string(out, &self.font, 22, 0)
+    }
+}
+
+fn write_sauce_string<W: Write>(out: &mut W, s: &str, size: usize, padding: u8) -> io::Result<()> {
+    let mut bytes : Vec<u8> = s.chars().map(|c| cp437::from_char(c).unwrap_or(b'?')).take(size).collect();
+    bytes.resize(size, padding);
+    out.write_all(&bytes)
+}
+
+/// How colors are written when saving ANSI art.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum AnsiColors {
+    /// Each color is replaced by the nearest of the 16 colors in `ANSI_PALETTE`,
+    /// except that background colors are limited to the first 8.
+    Classic,
+    /// As with `Classic`, but background colors may use all 16 colors, using the
+    /// blink attribute as BBS-era "iCE colors". The SAUCE record is flagged accordingly.
+    IceColors,
+    /// Colors are written exactly, as 24-bit color escape sequences. Not all viewers support these.
+    TrueColor,
+}
+
+/// The index of the color in `ANSI_PALETTE` nearest to the given color, searching only
+/// the first `limit` colors.
+fn nearest_ansi_color(c: Color, limit: usize) -> usize {
+    let distance = |p: &Color| {
+        let (dr, dg, db) = (p.r as i32 - c.r as i32, p.g as i32 - c.g as i32, p.b as i32 - c.b as i32);
+        dr * dr + dg * dg + db * db
+    };
+    (0..limit).min_by_key(|&i| distance(&ANSI_PALETTE[i])).unwrap_or(0)
+}
+
+/// The code page 437 character used for a tile that has no character in the tile set's character map,
+/// chosen from the block and shade characters by the tile's pixels.
+fn block_character(data: u64) -> u8 {
+    match semigraphics::nearest_quadrants(data) {
+        0 => b' ',
+        3 => 223,
+        5 => 221,
+        10 => 222,
+        12 => 220,
+        15 => 219,
+        p => 175 + (p as u32).count_ones() as u8,
+    }
+}
+
+/// Characters that ANSI viewers would take as control codes are replaced by similar looking ones.
+fn printable_character(c: u8) -> u8 {
+    match c {
+        0x07 => 0xF9,
+        0x08 | 0x0A => 0xDB,
+        0x09 => b'o',
+        0x0D => 0x0E,
+        0x1A => b'>',
+        0x1B => b'<',
+        c => c,
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum AnsiColor {
+    Index(u8),
+    Rgb(Color),
+}
+
+/// The current drawing state while interpreting an ANSI file.
+struct Pen {
+    fg: AnsiColor,
+    bg: AnsiColor,
+    bold: bool,
+    blink: bool,
+    reverse: bool,
+}
+
+impl Pen {
+    fn new() -> Pen {
+        Pen { fg: AnsiColor::Index(7), bg: AnsiColor::Index(0), bold: false, blink: false, reverse: false }
+    }
+    fn colors(&self, ice_colors: bool) -> (Color, Color) {
+        let fg = match self.fg {
+            AnsiColor::Index(i) => ANSI_PALETTE[(i + if self.bold && i < 8 { 8 } else { 0 }) as usize],
+            AnsiColor::Rgb(c) => c,
+        };
+        let bg = match self.bg {
+            AnsiColor::Index(i) => ANSI_PALETTE[(i + if self.blink && ice_colors && i < 8 { 8 } else { 0 }) as usize],
+            AnsiColor::Rgb(c) => c,
+        };
+        if self.reverse { (bg, fg) } else { (fg, bg) }
+    }
+    fn extended_color(params: &[u32], i: &mut usize) -> Option<AnsiColor> {
+        match params.get(*i + 1) {
+            Some(5) => {
+                *i += 2;
+                params.get(*i).map(|&n| AnsiColor::Rgb(xterm_color(n as u8)))
+            }
+            Some(2) => {
+                *i += 4;
+                if *i < params.len() {
+                    Some(AnsiColor::Rgb(rgb(params[*i - 2] as u8, params[*i - 1] as u8, params[*i] as u8)))
+                } else {
+                    None
+                }
+            }
+            _ => None,
+        }
+    }
+    fn select_graphic_rendition(&mut self, params: &[u32]) {
+        if params.is_empty() {
+            *self = Pen::new();
+        }
+        let mut i = 0;
+        while i < params.len() {
+            match params[i] {
+                0 => *self = Pen::new(),
+                1 => self.bold = true,
+                5 | 6 => self.blink = true,
+                7 => self.reverse = true,
+                22 => self.bold = false,
+                25 => self.blink = false,
+                27 => self.reverse = false,
+                n @ 30..=37 => self.fg = AnsiColor::Index((n - 30) as u8),
+                38 => if let Some(c) = Pen::extended_color(params, &mut i) { self.fg = c },
+                39 => self.fg = AnsiColor::Index(7),
+                n @ 40..=47 => self.bg = AnsiColor::Index((n - 40) as u8),
+                48 => if let Some(c) = Pen::extended_color(params, &mut i) { self.bg = c },
+                49 => self.bg = AnsiColor::Index(0),
+                n @ 90..=97 => self.fg = AnsiColor::Index((n - 90 + 8) as u8),
+                n @ 100..=107 => self.bg = AnsiColor::Index((n - 100 + 8) as u8),
+                _ => {}
+            }
+            i += 1;
+        }
+    }
+}
+
+/// The screen being drawn into while interpreting an ANSI file. Rows are added as needed.
+struct Screen {
+    width: u32,
+    rows: Vec<Vec<Option<Tile>>>,
+}
+
+impl Screen {
+    fn set(&mut self, x: u32, y: u32, tile: Option<Tile>) -> io::Result<()> {
+        if tile.is_none() && y as usize >= self.rows.len() {
+            return Ok(());
+        }
+        if y >= MAX_TILES / self.width {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "ANSI art is too large"));
+        }
+        while self.rows.len() <= y as usize {
+            self.rows.push(vec![None; self.width as usize]);
+        }
+        self.rows[y as usize][x as usize] = tile;
+        Ok(())
+    }
+}
+
+impl Graphic<()> {
+    /// Load ANSI art from the file at the given path. Sugar for `load_ansi_from` with `File::open`.
+    pub fn load_ansi_file<P: AsRef<Path>>(path: P, tile_set: &TileSet) -> io::Result<Graphic<()>> {
+        Graphic::load_ansi_from(File::open(path)?, tile_set)
+    }
+    /// Load ANSI art from any instance of the `Read` trait. Each character is mapped to a
+    /// tile using the character map of the given tile set, which should normally be `cga_ascii`.
+    ///
+    /// The graphic is as wide as the width given in the SAUCE record, or 80 tiles wide if there
+    /// isn't one, and as tall as needed to fit the art. Text wraps at the right-hand edge.
+    /// Supports the common cursor movement and color escape sequences, including 256 color and
+    /// 24-bit color extensions. Anything after an end-of-file character (`0x1A`) is ignored.
+    /// Art that would be more than 4,194,304 tiles in size is rejected as invalid data.
+    pub fn load_ansi_from<R: Read>(mut input: R, tile_set: &TileSet) -> io::Result<Graphic<()>> {
+        let mut data = Vec::new();
+        input.read_to_end(&mut data)?;
+        let sauce = Sauce::from_bytes(&data);
+        let width = match sauce {
+            Some(ref s) if s.data_type == 1 && s.width > 0 => s.width as u32,
+            _ => 80,
+        };
+        let ice_colors = sauce.as_ref().is_some_and(|s| s.ice_colors());
+        let end = data.iter().position(|&b| b == 0x1A).unwrap_or(data.len());
+        let mut screen = Screen { width, rows: Vec::new() };
+        let mut pen = Pen::new();
+        let (mut x, mut y) = (0u32, 0u32);
+        let mut saved = (0, 0);
+        let mut i = 0;
+        while i < end {
+            let b = data[i];
+            i += 1;
+            match b {
+                b'\r' => x = 0,
+                b'\n' => { x = 0; y = y.saturating_add(1) },
+                b'\t' => x = ((x / 8 + 1) * 8).min(width - 1),
+                0x1B if i < end && data[i] == b'[' => {
+                    i += 1;
+                    let start = i;
+                    while i < end && !(0x40..=0x7E).contains(&data[i]) {
+                        i += 1;
+                    }
+                    if i >= end {
+                        break;
+                    }
+                    let command = data[i];
+                    let params : Vec<u32> = String::from_utf8_lossy(&data[start..i])
+                        .trim_start_matches(['?', '='])
+                        .split(';')
+                        .filter_map(|p| p.parse().ok())
+                        .collect();
+                    i += 1;
+                    let n = params.first().cloned().unwrap_or(1).max(1);
+                    match command {
+                        b'A' => y = y.saturating_sub(n),
+                        b'B' => y = y.saturating_add(n),
+                        b'C' => x = x.saturating_add(n).min(width - 1),
+                        b'D' => x = x.saturating_sub(n),
+                        b'H' | b'f' => {
+                            y = params.first().cloned().unwrap_or(1).max(1) - 1;
+                            x = (params.get(1).cloned().unwrap_or(1).max(1) - 1).min(width - 1);
+                        }
+                        b's' => saved = (x, y),
+                        b'u' => { x = saved.0; y = saved.1 },
+                        b'J' if params.first() == Some(&2) => {
+                            screen.rows.clear();
+                            x = 0;
+                            y = 0;
+                        }
+                        b'K' => {
+                            let (from, to) = match params.first() {
+                                Some(1) => (0, x.min(width - 1) + 1),
+                                Some(2) => (0, width),
+                                _ => (x, width),
+                            };
+                            for cx in from..to {
+                                screen.set(cx, y, None)?;
+                            }
+                        }
+                        b'm' => pen.select_graphic_rendition(&params),
+                        _ => {}
+                    }
+                }
+                _ => {
+                    // Wrapping is deferred until the next character, so that a line break
+                    // straight after a full line doesn't leave an empty line.
+                    if x >= width {
+                        x = 0;
+                        y += 1;
+                    }
+                    let (fg, bg) = pen.colors(ice_colors);
+                    screen.set(x, y, Some(Tile { index: tile_set[b as char], fg, bg }))?;
+                    x += 1;
+                }
+            }
+        }
+        let blank = Tile { index: tile_set[' '], fg: ANSI_PALETTE[7], bg: ANSI_PALETTE[0] };
+        let mut g = Graphic::solid(width, screen.rows.len().max(1) as u32, blank);
+        for (y, row) in screen.rows.iter().enumerate() {
+            for (x, t) in row.iter().enumerate() {
+                if let Some(t) = t {
+                    g.set_tile(x as u32, y as u32, *t);
+                }
+            }
+        }
+        Ok(g)
+    }
+}
+
+impl <T> Graphic<T> {
+    /// Save the graphic as ANSI art to some instance of `Write` (such as a file), followed by a SAUCE record.
+    /// Each tile is written as the character the tile set's character map gives for it, so the tile set
+    /// would usually be `cga_ascii`. Tiles with no character are replaced by the closest block character.
+    ///
+    /// The SAUCE record is based on the given one, but with its data type, file type,
+    /// dimensions and color flags set to describe the graphic.
+    pub fn save_ansi<W: Write>(&self, file: &mut W, tile_set: &TileSet, colors: AnsiColors, sauce: &Sauce) -> io::Result<()> {
+        let chars = tile_set.reverse_char_map();
+        let mut out = Vec::new();
+        out.extend_from_slice(b"\x1b[0m");
+        let mut current = None;
+        for y in 0..self.height {
+            let cells : Vec<(u8, Color, Color)> = (0..self.width).map(|x| {
+                let t = self.get_tile(x, y);
+                let c = match chars.get(t.index).cloned().flatten() {
+                    Some(c) => c as u8,
+                    None => block_character(if t.index < tile_set.len() { tile_set[t.index] } else { 0 }),
+                };
+                let (fg, bg) = match colors {
+                    AnsiColors::TrueColor => (t.fg, t.bg),
+                    AnsiColors::Classic => (ANSI_PALETTE[nearest_ansi_color(t.fg, 16)], ANSI_PALETTE[nearest_ansi_color(t.bg, 8)]),
+                    AnsiColors::IceColors => (ANSI_PALETTE[nearest_ansi_color(t.fg, 16)], ANSI_PALETTE[nearest_ansi_color(t.bg, 16)]),
+                };
+                (printable_character(c), fg, bg)
+            }).collect();
+            // Blank cells at the end of a line can be left out, as they are black when loaded.
+            let black = Color::RGB(0, 0, 0);
+            let len = cells.iter().rposition(|&(c, _, bg)| !((c == b' ' || c == 0) && bg == black)).map_or(0, |i| i + 1);
+            for &(c, fg, bg) in &cells[..len] {
+                if current != Some((fg, bg)) {
+                    match colors {
+                        AnsiColors::TrueColor =>
+                            write!(out, "\x1b[38;2;{};{};{};48;2;{};{};{}m", fg.r, fg.g, fg.b, bg.r, bg.g, bg.b)?,
+                        _ => {
+                            let (f, b) = (nearest_ansi_color(fg, 16), nearest_ansi_color(bg, 16));
+                            write!(out, "\x1b[0;{}{}3{};4{}m", if f >= 8 { "1;" } else { "" }, if b >= 8 { "5;" } else { "" }, f % 8, b % 8)?;
+                        }
+                    }
+                    current = Some((fg, bg));
+                }
+                out.push(c);
+            }
+            // A line break straight after a full line is taken as its wrap when loaded, so every line ends with one,
+            // which also keeps an empty line after a full one from being lost.
+            if current.is_some_and(|(_, bg)| bg != black) {
+                out.extend_from_slice(b"\x1b[0m");
+                current = None;
+            }
+            out.extend_from_slice(b"\r\n");
+        }
+        out.extend_from_slice(b"\x1b[0m");
+        file.write_all(&out)?;
+        file.write_all(&[0x1A])?;
+        let sauce = Sauce {
+            data_type: 1,
+            file_type: 1,
+            width: self.width as u16,
+            height: self.height as u16,
+            flags: if colors == AnsiColors::IceColors { sauce.flags | 1 } else { sauce.flags & !1 },
+            ..sauce.clone()
+        };
+        sauce.write_to(file, out.len() as u32)
+    }
+}
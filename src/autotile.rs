@@ -0,0 +1,215 @@
+//! Autotiling: painting terrain, such as water or walls, onto a graphic and having the right
+//! edge and corner tiles chosen for each cell automatically, depending on its neighbours.
+//!
+//! ```ignore
+//! let mut tiler = AutoTiler::new();
+//! let water = tiler.add_terrain(Terrain::edges(water_tiles, colors::BLUE, colors::DARK_BLUE));
+//! tiler.paint(&mut level, 4, 3, water);
+//! ```
+//!
+//! Each terrain is a group of tile indices, one for each arrangement of neighbours of the same
+//! terrain, which is given as a mask of the `NORTH`, `EAST`, `SOUTH` and `WEST` bits, plus for
+//! blob terrains the bits for the four diagonals. A cell's terrain is worked out from its tile
+//! index, so graphics need nothing beyond their tiles to be autotiled, and cells of other tiles
+//! are left alone.
+
+use std::collections::BTreeMap;
+
+use super::{Color, Graphic, Tile};
+
+/// The neighbour above.
+pub const NORTH: u8 = 1;
+/// The neighbour to the right.
+pub const EAST: u8 = 2;
+/// The neighbour below.
+pub const SOUTH: u8 = 4;
+/// The neighbour to the left.
+pub const WEST: u8 = 8;
+/// The neighbour above and to the right.
+pub const NORTH_EAST: u8 = 16;
+/// The neighbour below and to the right.
+pub const SOUTH_EAST: u8 = 32;
+/// The neighbour below and to the left.
+pub const SOUTH_WEST: u8 = 64;
+/// The neighbour above and to the left.
+pub const NORTH_WEST: u8 = 128;
+
+/// Each neighbour's bit, with its offset from the cell.
+const NEIGHBOURS: [(u8, i64, i64); 8] = [
+    (NORTH, 0, -1), (EAST, 1, 0), (SOUTH, 0, 1), (WEST, -1, 0),
+    (NORTH_EAST, 1, -1), (SOUTH_EAST, 1, 1), (SOUTH_WEST, -1, 1), (NORTH_WEST, -1, -1),
+];
+
+/// Which neighbours of a cell decide its tile.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Adjacency {
+    /// The four neighbours sharing an edge with the cell, for sixteen tiles (Wang tiles).
+    Edges,
+    /// All eight neighbours, for up to forty-seven tiles (blob tiles). A diagonal neighbour only
+    /// counts if both of the edge neighbours next to it are of the same terrain too.
+    Blob,
+}
+
+impl Adjacency {
+    /// The mask with the neighbours that don't count under this adjacency removed.
+    pub fn normalize(self, mask: u8) -> u8 {
+        let edges = mask & (NORTH | EAST | SOUTH | WEST);
+        match self {
+            Adjacency::Edges => edges,
+            Adjacency::Blob => {
+                let corner = |c: u8, a: u8, b: u8| if mask & c != 0 && edges & a != 0 && edges & b != 0 { c } else { 0 };
+                edges | corner(NORTH_EAST, NORTH, EAST) | corner(SOUTH_EAST, SOUTH, EAST)
+                    | corner(SOUTH_WEST, SOUTH, WEST) | corner(NORTH_WEST, NORTH, WEST)
+            }
+        }
+    }
+}
+
+/// A kind of terrain: the tiles used for it, depending on its neighbours, and the colors it is painted in.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Terrain {
+    adjacency: Adjacency,
+    tiles: BTreeMap<u8, usize>,
+    pub fg: Color,
+    pub bg: Color,
+}
+
+impl Terrain {
+    /// A terrain using the four edge neighbours, where `tiles[mask]` is the tile index for each
+    /// mask of the `NORTH`, `EAST`, `SOUTH` and `WEST` neighbours of the same terrain.
+    pub fn edges(tiles: [usize; 16], fg: Color, bg: Color) -> Terrain {
+        Terrain { adjacency: Adjacency::Edges, tiles: (0..16).map(|m| (m as u8, tiles[m])).collect(), fg, bg }
+    }
+    /// A terrain using all eight neighbours, with the tile index for each mask of neighbours of the same terrain.
+    /// Masks are normalized as described for `Adjacency::Blob`, so only the (at most forty-seven) normalized masks
+    /// need a tile. For any mask without one, the tile for its edge neighbours alone is used, or else the tile for
+    /// no neighbours.
+    pub fn blob(tiles: &[(u8, usize)], fg: Color, bg: Color) -> Terrain {
+        let tiles = tiles.iter().map(|&(m, t)| (Adjacency::Blob.normalize(m), t)).collect();
+        Terrain { adjacency: Adjacency::Blob, tiles, fg, bg }
+    }
+    /// Which neighbours decide the terrain's tiles.
+    pub fn adjacency(&self) -> Adjacency {
+        self.adjacency
+    }
+    /// The tile index used for the given mask of neighbours of the same terrain, if the terrain has one.
+    pub fn tile_for(&self, mask: u8) -> Option<usize> {
+        let mask = self.adjacency.normalize(mask);
+        self.tiles.get(&mask)
+            .or_else(|| self.tiles.get(&(mask & (NORTH | EAST | SOUTH | WEST))))
+            .or_else(|| self.tiles.get(&0))
+            .copied()
+    }
+    /// Whether the tile index is one of the terrain's tiles.
+    pub fn contains(&self, index: usize) -> bool {
+        self.tiles.values().any(|&t| t == index)
+    }
+}
+
+/// A set of terrains, which paints them onto graphics and chooses the tiles for them.
+///
+/// Terrains are identified by the number `add_terrain` returns. If terrains share tile indices,
+/// a cell with one of those tiles is taken to be of the terrain added first.
+#[derive(Clone, Debug, Default)]
+pub struct AutoTiler {
+    terrains: Vec<Terrain>,
+    /// Whether cells beyond the edges of the graphic count as the same terrain as their neighbours,
+    /// so that terrain reaching the edge continues past it rather than having a border there.
+    pub border_matches: bool,
+}
+
+impl AutoTiler {
+    /// An autotiler without any terrains.
+    pub fn new() -> AutoTiler {
+        AutoTiler { terrains: Vec::new(), border_matches: false }
+    }
+    /// Add a terrain, returning the number that identifies it.
+    pub fn add_terrain(&mut self, terrain: Terrain) -> usize {
+        self.terrains.push(terrain);
+        self.terrains.len() - 1
+    }
+    /// The terrain with the given number, if there is one.
+    pub fn terrain(&self, terrain: usize) -> Option<&Terrain> {
+        self.terrains.get(terrain)
+    }
+    /// The terrain with the given number, if there is one, for changing its tiles or colors.
+    pub fn terrain_mut(&mut self, terrain: usize) -> Option<&mut Terrain> {
+        self.terrains.get_mut(terrain)
+    }
+    /// The number of the terrain of the tile at the given position in a graphic, if any.
+    pub fn terrain_at<T>(&self, graphic: &Graphic<T>, x: u32, y: u32) -> Option<usize> {
+        if x >= graphic.width || y >= graphic.height {
+            return None;
+        }
+        let index = graphic.get_tile(x, y).index;
+        self.terrains.iter().position(|t| t.contains(index))
+    }
+    /// The mask of the neighbours of the given position that are of the given terrain.
+    pub fn mask_at<T>(&self, graphic: &Graphic<T>, x: u32, y: u32, terrain: usize) -> u8 {
+        let mut mask = 0;
+        for &(bit, dx, dy) in NEIGHBOURS.iter() {
+            let (nx, ny) = (x as i64 + dx, y as i64 + dy);
+            let same = if nx < 0 || ny < 0 || nx >= graphic.width as i64 || ny >= graphic.height as i64 {
+                self.border_matches
+            } else {
+                self.terrain_at(graphic, nx as u32, ny as u32) == Some(terrain)
+            };
+            if same {
+                mask |= bit;
+            }
+        }
+        mask
+    }
+    /// Paint the given terrain at a position in a graphic, in the terrain's colors, and choose the tiles
+    /// for it and its neighbours. Does nothing if the position is out of bounds or there is no such terrain.
+    pub fn paint<T>(&self, graphic: &mut Graphic<T>, x: u32, y: u32, terrain: usize) {
+        self.paint_rect(graphic, x, y, 1, 1, terrain)
+    }
+    /// Paint the given terrain over a rectangle of a graphic, as `paint` does for a single position.
+    pub fn paint_rect<T>(&self, graphic: &mut Graphic<T>, x: u32, y: u32, width: u32, height: u32, terrain: usize) {
+        let t = match self.terrains.get(terrain) {
+            Some(t) => t,
+            None => return,
+        };
+        if let Some(index) = t.tile_for(0) {
+            graphic.draw_rect(x, y, width, height, Tile { index, fg: t.fg, bg: t.bg });
+            self.retile_around(graphic, x, y, width, height);
+        }
+    }
+    /// Replace the tile at a position in a graphic, such as with a blank tile to erase terrain there,
+    /// and choose the tiles for its neighbours again.
+    pub fn erase<T>(&self, graphic: &mut Graphic<T>, x: u32, y: u32, tile: Tile) {
+        graphic.set_tile(x, y, tile);
+        self.retile_around(graphic, x, y, 1, 1);
+    }
+    /// Choose the tiles again for every cell of a terrain in a rectangle of a graphic, keeping their colors,
+    /// such as after the graphic has been changed by other means.
+    pub fn retile<T>(&self, graphic: &mut Graphic<T>, x: u32, y: u32, width: u32, height: u32) {
+        let right = x.saturating_add(width).min(graphic.width);
+        let bottom = y.saturating_add(height).min(graphic.height);
+        // The terrain of each cell is found before any tiles change, as changing them can't change it.
+        let mut cells = Vec::new();
+        for j in y..bottom {
+            for i in x..right {
+                if let Some(terrain) = self.terrain_at(graphic, i, j) {
+                    let index = self.terrains[terrain].tile_for(self.mask_at(graphic, i, j, terrain));
+                    cells.push((i, j, index));
+                }
+            }
+        }
+        for (i, j, index) in cells {
+            let tile = graphic.get_tile(i, j);
+            match index {
+                Some(index) if index != tile.index => graphic.set_tile(i, j, Tile { index, ..tile }),
+                _ => {}
+            }
+        }
+    }
+    /// Choose the tiles again for a rectangle and the cells around it.
+    fn retile_around<T>(&self, graphic: &mut Graphic<T>, x: u32, y: u32, width: u32, height: u32) {
+        let (left, top) = (x.saturating_sub(1), y.saturating_sub(1));
+        let right = x.saturating_add(width).saturating_add(1);
+        let bottom = y.saturating_add(height).saturating_add(1);
+        self.retile(graphic, left, top, right - left, bottom - top);
+    }
+}
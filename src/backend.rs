@@ -0,0 +1,200 @@
+//! The interface between graphics and whatever renders them.
+
+use super::{Graphic, Tile, TileSet};
+
+/// The most separate regions of changed tiles a graphic keeps track of before merging them all into one.
+const MAX_DAMAGE: usize = 16;
+
+/// A rectangle of a graphic, in tiles or in pixels.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Region {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Region {
+    /// The smallest region containing both regions.
+    pub fn union(self, other: Region) -> Region {
+        let (x, y) = (self.x.min(other.x), self.y.min(other.y));
+        let right = (self.x + self.width).max(other.x + other.width);
+        let bottom = (self.y + self.height).max(other.y + other.height);
+        Region { x, y, width: right - x, height: bottom - y }
+    }
+    /// Whether the regions overlap or are next to each other.
+    fn touches(self, other: Region) -> bool {
+        self.x <= other.x + other.width && other.x <= self.x + self.width
+            && self.y <= other.y + other.height && other.y <= self.y + self.height
+    }
+}
+
+/// Something that renders the tiles of a `Graphic`, such as an SDL texture (the type parameter of `Graphic`).
+///
+/// A `Graphic<B>` keeps track of which of its tiles the backend has already rendered, so `update_texture`
+/// only passes on tiles that have changed. Implement this trait (along with `Draw`) to plug in your own renderer,
+/// and attach it to a graphic with `with_backend`.
+pub trait Backend {
+    /// Render a tile of the graphic, at the given position in tiles, using the given tile set.
+    /// Returns whether anything had to be rendered.
+    fn update_tile(&mut self, x: u32, y: u32, tile: Tile, tile_set: &TileSet) -> bool;
+    /// Render a region of the graphic (in tiles) in which some tiles have changed. `tiles` holds every tile
+    /// in the region row by row, each with whether it has changed since it was last rendered.
+    /// Returns how many tiles had to be rendered.
+    ///
+    /// By default this passes each changed tile to `update_tile`. Backends that can render a whole region
+    /// at once more cheaply, such as an SDL texture that is locked once for the region, can override it.
+    fn update_region(&mut self, region: Region, tiles: &[(Tile, bool)], tile_set: &TileSet) -> u32 {
+        let mut count = 0;
+        for (i, &(tile, changed)) in tiles.iter().enumerate() {
+            let (x, y) = (region.x + i as u32 % region.width, region.y + i as u32 / region.width);
+            if changed && self.update_tile(x, y, tile, tile_set) {
+                count += 1;
+            }
+        }
+        count
+    }
+    /// Forget anything that has been rendered, as the tile set may have changed. Called by `mark_dirty`,
+    /// after which every tile is passed to `update_tile` again.
+    fn invalidate(&mut self) {}
+    /// Whether what the backend had rendered has been lost by other means than `invalidate`, such as by
+    /// another graphic sharing it being marked dirty. `update_texture` then marks the graphic dirty itself.
+    fn needs_full_update(&self) -> bool {
+        false
+    }
+}
+
+/// A backend that can draw what it has rendered onto some target, such as an SDL `Canvas`.
+pub trait Draw<Target: ?Sized>: Backend + Sized {
+    /// Draw the graphic to the target with its top-left corner at the given position,
+    /// in the target's units (pixels for SDL, characters for a terminal).
+    fn draw(graphic: &Graphic<Self>, target: &mut Target, position: (i32, i32));
+}
+
+impl <T> Graphic<T> {
+    /// Record that the tiles in the given rectangle may have changed, so that `update_texture` looks at them.
+    /// Regions that overlap or touch are merged, and if there are too many they are all merged into one.
+    pub(crate) fn add_damage(&mut self, x: u32, y: u32, width: u32, height: u32) {
+        if x >= self.width || y >= self.height || width == 0 || height == 0 {
+            return;
+        }
+        let mut region = Region { x, y, width: width.min(self.width - x), height: height.min(self.height - y) };
+        let mut i = 0;
+        while i < self.damage.len() {
+            if self.damage[i].touches(region) {
+                // The merged region may now touch regions already passed over.
+                region = region.union(self.damage.swap_remove(i));
+                i = 0;
+            } else {
+                i += 1;
+            }
+        }
+        self.damage.push(region);
+        if self.damage.len() > MAX_DAMAGE {
+            let all = self.damage.iter().copied().reduce(Region::union);
+            self.damage = all.into_iter().collect();
+        }
+    }
+}
+
+impl <B: Backend> Graphic<B> {
+    /// Instructs the next invocation of update_texture to redraw all tiles, regardless of whether it thinks they need redrawing.
+    pub fn mark_dirty(&mut self) {
+        for i in 0..self.dirty.len() {
+            self.dirty[i] = None
+        }
+        self.add_damage(0, 0, self.width, self.height);
+        self.texture.invalidate();
+    }
+    /// Render each tile that needs redrawing in the graphic using the provided tile set.
+    /// Returns number of tiles redrawn.
+    pub fn update_texture(&mut self, tile_set : &TileSet) -> u32 {
+        self.update_regions(tile_set).0
+    }
+    /// Render each tile that needs redrawing, as `update_texture` does, and return the rectangles (in pixels,
+    /// relative to the top-left of the graphic) that have changed, so that only those need to be presented.
+    pub fn update_texture_regions(&mut self, tile_set : &TileSet) -> Vec<Region> {
+        self.update_regions(tile_set).1
+    }
+    fn update_regions(&mut self, tile_set : &TileSet) -> (u32, Vec<Region>) {
+        if self.texture.needs_full_update() {
+            self.mark_dirty();
+        }
+        self.check_tile_set(tile_set);
+        let mut c = 0;
+        let mut changed_regions = Vec::new();
+        let mut tiles = Vec::new();
+        for region in std::mem::take(&mut self.damage) {
+            // Only the tiles within the bounds of those that changed are passed on.
+            let mut bounds : Option<Region> = None;
+            for y in region.y..region.y + region.height {
+                for x in region.x..region.x + region.width {
+                    let i = (x + y * self.width) as usize;
+                    if self.dirty[i] != Some(self.tiles[i]) {
+                        let r = Region { x, y, width: 1, height: 1 };
+                        bounds = Some(bounds.map_or(r, |b| b.union(r)));
+                    }
+                }
+            }
+            let bounds = match bounds {
+                Some(b) => b,
+                None => continue,
+            };
+            tiles.clear();
+            for y in bounds.y..bounds.y + bounds.height {
+                for x in bounds.x..bounds.x + bounds.width {
+                    let i = (x + y * self.width) as usize;
+                    let t = self.tiles[i];
+                    tiles.push((t, self.dirty[i] != Some(t)));
+                    self.dirty[i] = Some(t);
+                }
+            }
+            c += self.texture.update_region(bounds, &tiles, tile_set);
+            changed_regions.push(Region { x: bounds.x * 8, y: bounds.y * 8, width: bounds.width * 8, height: bounds.height * 8 });
+        }
+        (c, changed_regions)
+    }
+    /// Mark the tiles affected by any changes to the tile set since the last update as needing redrawing.
+    /// Backends that cache anything rendered from the tile set check its version themselves.
+    fn check_tile_set(&mut self, tile_set : &TileSet) {
+        let version = tile_set.version();
+        let seen = match self.tile_set_version.replace(version) {
+            Some(seen) if seen != version => seen,
+            _ => return,
+        };
+        match tile_set.tiles_changed_since(seen) {
+            Some(changed) if !tile_set.char_map_changed_since(seen) => {
+                let mut affected = vec![false; tile_set.len()];
+                for i in changed {
+                    affected[i] = true;
+                }
+                for y in 0..self.height {
+                    for x in 0..self.width {
+                        let i = (x + y * self.width) as usize;
+                        if affected.get(self.tiles[i].index) == Some(&true) {
+                            self.dirty[i] = None;
+                            self.add_damage(x, y, 1, 1);
+                        }
+                    }
+                }
+            }
+            // A different tile set, or a changed character map, can affect any tile.
+            _ => {
+                for d in self.dirty.iter_mut() {
+                    *d = None;
+                }
+                self.add_damage(0, 0, self.width, self.height);
+            }
+        }
+    }
+    /// Draw the graphic to the target (for example an SDL `Canvas`) at the provided position.
+    /// Note that you may wish to call `update_texture` and provide a tile set first, as this simply draws
+    /// what has already been rendered.
+    pub fn draw<C: ?Sized, P: Into<(i32, i32)>>(&self, target: &mut C, position: P) where B: Draw<C> {
+        B::draw(self, target, position.into())
+    }
+    /// The backend rendering this graphic.
+    pub fn backend(&self) -> &B {
+        &self.texture
+    }
+}
@@ -0,0 +1,152 @@
+//! Bundle files (`.tsb`), which keep a tile set and swatch together with named graphics
+//! and stamps drawn with them and any metadata, such as a project's name or author.
+//!
+//! ```ignore
+//! let bundle = Bundle::load_file("level1.tsb")?;
+//! let title = bundle.graphic("title").unwrap().tile_cache_textured(&texture_creator);
+//! ```
+//!
+//! A bundle file starts with the magic number `tsrb` and a version byte, followed by the
+//! metadata, the tile set and swatch (in the formats of `TileSet::save` and `Swatch::save`),
+//! the stamps (in the format of `StampLibrary::save`) and then the graphics (in the format of
+//! `Graphic::save_compressed`). Strings and each of the sections are preceded by their length
+//! in bytes. Version 1 bundles, which have no stamps, can still be loaded.
+
+use std::fs::File;
+use std::io;
+use std::io::{Cursor, Read, Write};
+use std::path::Path;
+use std::collections::BTreeMap;
+
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+
+use super::{Graphic, Swatch, TileSet};
+use super::stamp::StampLibrary;
+
+const MAGIC: &[u8; 4] = b"tsrb";
+const VERSION: u8 = 2;
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+pub(crate) fn write_section<W: Write>(file: &mut W, data: &[u8]) -> io::Result<()> {
+    file.write_u32::<LittleEndian>(data.len() as u32)?;
+    file.write_all(data)
+}
+
+pub(crate) fn read_section<R: Read>(input: &mut R) -> io::Result<Vec<u8>> {
+    let len = input.read_u32::<LittleEndian>()?;
+    let mut data = Vec::new();
+    input.take(len as u64).read_to_end(&mut data)?;
+    if data.len() != len as usize {
+        return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "file is truncated"));
+    }
+    Ok(data)
+}
+
+pub(crate) fn read_string<R: Read>(input: &mut R) -> io::Result<String> {
+    String::from_utf8(read_section(input)?).map_err(|_| invalid("file contains a string that isn't UTF-8"))
+}
+
+/// A tile set and swatch, and the graphics and stamps drawn with them, each with a name.
+#[derive(Clone)]
+pub struct Bundle {
+    pub tile_set: TileSet,
+    pub swatch: Swatch,
+    pub graphics: BTreeMap<String, Graphic<()>>,
+    /// The pieces the graphics are built from.
+    pub stamps: StampLibrary,
+    /// Any other information about the bundle, such as its title and author.
+    pub metadata: BTreeMap<String, String>,
+}
+
+impl Bundle {
+    /// A bundle of the given tile set and swatch, with no graphics, stamps or metadata.
+    pub fn new(tile_set: TileSet, swatch: Swatch) -> Bundle {
+        Bundle { tile_set, swatch, graphics: BTreeMap::new(), stamps: StampLibrary::new(), metadata: BTreeMap::new() }
+    }
+    /// The graphic with the given name, if there is one.
+    pub fn graphic(&self, name: &str) -> Option<&Graphic<()>> {
+        self.graphics.get(name)
+    }
+    /// The graphic with the given name, if there is one, for editing.
+    pub fn graphic_mut(&mut self, name: &str) -> Option<&mut Graphic<()>> {
+        self.graphics.get_mut(name)
+    }
+    /// Add a copy of a graphic to the bundle with the given name, returning the graphic it replaces, if any.
+    pub fn insert_graphic<T, S: Into<String>>(&mut self, name: S, graphic: &Graphic<T>) -> Option<Graphic<()>> {
+        let mut g = Graphic::blank(graphic.width, graphic.height);
+        g.copy_all_tiles_from(graphic, 0, 0);
+        self.graphics.insert(name.into(), g)
+    }
+    /// The metadata value with the given key, if there is one.
+    pub fn metadata(&self, key: &str) -> Option<&str> {
+        self.metadata.get(key).map(|v| v.as_str())
+    }
+    /// Load a bundle from the file with the given path. Sugar for `load_from` with `File::open`.
+    pub fn load_file<P: AsRef<Path>>(path: P) -> io::Result<Bundle> {
+        Bundle::load_from(File::open(path)?)
+    }
+    /// Load a bundle from any instance of the `Read` trait.
+    pub fn load_from<R: Read>(mut input: R) -> io::Result<Bundle> {
+        let mut magic = [0; 4];
+        input.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(invalid("not a tesserae bundle"));
+        }
+        let version = input.read_u8()?;
+        if version == 0 || version > VERSION {
+            return Err(invalid("unsupported bundle version"));
+        }
+        let mut metadata = BTreeMap::new();
+        for _ in 0..input.read_u32::<LittleEndian>()? {
+            let key = read_string(&mut input)?;
+            metadata.insert(key, read_string(&mut input)?);
+        }
+        let tile_set = TileSet::load_from(Cursor::new(read_section(&mut input)?))?;
+        let swatch = Swatch::load_from(Cursor::new(read_section(&mut input)?));
+        let stamps = if version >= 2 {
+            StampLibrary::load_from(Cursor::new(read_section(&mut input)?))?
+        } else {
+            StampLibrary::new()
+        };
+        let mut graphics = BTreeMap::new();
+        for _ in 0..input.read_u32::<LittleEndian>()? {
+            let name = read_string(&mut input)?;
+            graphics.insert(name, Graphic::load_from(Cursor::new(read_section(&mut input)?))?);
+        }
+        Ok(Bundle { tile_set, swatch, graphics, stamps, metadata })
+    }
+    /// Save the bundle to some instance of `Write` (such as a file).
+    pub fn save<W: Write>(&self, file: &mut W) -> io::Result<()> {
+        file.write_all(MAGIC)?;
+        file.write_u8(VERSION)?;
+        file.write_u32::<LittleEndian>(self.metadata.len() as u32)?;
+        for (key, value) in &self.metadata {
+            write_section(file, key.as_bytes())?;
+            write_section(file, value.as_bytes())?;
+        }
+        let mut data = Vec::new();
+        self.tile_set.save(&mut data)?;
+        write_section(file, &data)?;
+        data.clear();
+        self.swatch.save(&mut data)?;
+        write_section(file, &data)?;
+        data.clear();
+        self.stamps.save(&mut data)?;
+        write_section(file, &data)?;
+        file.write_u32::<LittleEndian>(self.graphics.len() as u32)?;
+        for (name, graphic) in &self.graphics {
+            write_section(file, name.as_bytes())?;
+            data.clear();
+            graphic.save_compressed(&mut data)?;
+            write_section(file, &data)?;
+        }
+        Ok(())
+    }
+    /// Save the bundle to a file at the provided path.
+    pub fn store<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        self.save(&mut File::create(path)?)
+    }
+}
@@ -0,0 +1,224 @@
+//! Loading and saving Commodore 64 text screens and character sets, for use with
+//! the built-in `petscii` and `petscii_unshifted` tile sets and tools from the C64 scene.
+//!
+//! A C64 text screen is 40x25 characters, given by 1000 bytes of screen RAM (screen codes)
+//! and 1000 bytes of color RAM (a foreground color for each character), against a single
+//! background color shared by the whole screen.
+//!
+//! The built-in PETscii tile sets don't store characters in screen code order: the first 64
+//! screen codes are stored with their two halves swapped, so that the tile set starts with
+//! space and the digits. `tile_index` and `screen_code` convert between the two, and
+//! character sets loaded with `load_charset_from` use the same arrangement.
+
+use std::fs::File;
+use std::io;
+use std::io::{Read, Write};
+use std::path::Path;
+use std::collections::HashMap;
+
+use super::{Color, Graphic, Swatch, Tile, TileSet};
+
+const fn rgb(r: u8, g: u8, b: u8) -> Color {
+    Color { r, g, b, a: 255 }
+}
+
+/// The 16 colors of the C64, in the order of their color numbers
+/// (black, white, red, cyan, purple, green, blue, yellow, orange, brown,
+/// light red, dark grey, grey, light green, light blue, light grey).
+pub const C64_PALETTE: [Color; 16] = [
+    rgb(0x00, 0x00, 0x00), rgb(0xFF, 0xFF, 0xFF), rgb(0x68, 0x37, 0x2B), rgb(0x70, 0xA4, 0xB2),
+    rgb(0x6F, 0x3D, 0x86), rgb(0x58, 0x8D, 0x43), rgb(0x35, 0x28, 0x79), rgb(0xB8, 0xC7, 0x6F),
+    rgb(0x6F, 0x4F, 0x25), rgb(0x43, 0x39, 0x00), rgb(0x9A, 0x67, 0x59), rgb(0x44, 0x44, 0x44),
+    rgb(0x6C, 0x6C, 0x6C), rgb(0x9A, 0xD2, 0x84), rgb(0x6C, 0x5E, 0xB5), rgb(0x95, 0x95, 0x95),
+];
+
+const WIDTH: u32 = 40;
+const HEIGHT: u32 = 25;
+const SCREEN_SIZE: usize = (WIDTH * HEIGHT) as usize;
+const CHARSET_SIZE: usize = 256 * 8;
+
+/// The tile index in the built-in PETscii tile sets of the given screen code.
+pub fn tile_index(code: u8) -> usize {
+    if code & 0x40 == 0 { (code ^ 0x20) as usize } else { code as usize }
+}
+
+/// The screen code of the given tile index in the built-in PETscii tile sets.
+/// Tiles from 256 onwards are taken to be a second character set, in the same arrangement.
+pub fn screen_code(index: usize) -> u8 {
+    tile_index(index as u8) as u8
+}
+
+/// Which characters screen codes 128 to 255 show.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Charset {
+    /// The C64's character ROM, as in the built-in PETscii tile sets: screen codes
+    /// 128 to 255 show codes 0 to 127 in reverse video, so they are drawn by swapping
+    /// the foreground and background colors.
+    Rom,
+    /// A custom character set, where all 256 screen codes are separate characters.
+    Custom,
+}
+
+/// A C64 text mode screen.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Screen {
+    /// 1000 screen codes, row by row.
+    pub codes: Vec<u8>,
+    /// 1000 foreground colors (0 to 15), row by row.
+    pub colors: Vec<u8>,
+    pub border: u8,
+    pub background: u8,
+}
+
+impl Screen {
+    /// A screen full of spaces, in light blue on blue like the C64's start up screen.
+    pub fn blank() -> Screen {
+        Screen { codes: vec![0x20; SCREEN_SIZE], colors: vec![14; SCREEN_SIZE], border: 14, background: 6 }
+    }
+    /// Load a screen from the file with the given path. Program files (ending in `.prg`)
+    /// are loaded with `load_prg_from` and any others with `load_from`.
+    pub fn load_file<P: AsRef<Path>>(path: P) -> io::Result<Screen> {
+        let prg = path.as_ref().extension().is_some_and(|e| e.eq_ignore_ascii_case("prg"));
+        let f = File::open(path)?;
+        if prg { Screen::load_prg_from(f) } else { Screen::load_from(f) }
+    }
+    /// Load a screen from a `Read` instance such as a file, containing 1000 bytes of screen RAM,
+    /// optionally followed by 1000 bytes of color RAM and then the border and background colors.
+    /// The color RAM may also start 1024 bytes in, as in dumps of whole 1KB blocks of memory.
+    /// Anything missing is taken from `Screen::blank`.
+    pub fn load_from<R: Read>(mut input: R) -> io::Result<Screen> {
+        let mut data = Vec::new();
+        input.read_to_end(&mut data)?;
+        if data.len() < SCREEN_SIZE {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "C64 screen data is shorter than 1000 bytes"));
+        }
+        let mut screen = Screen::blank();
+        screen.codes.copy_from_slice(&data[..SCREEN_SIZE]);
+        let start = if data.len() >= 1024 + SCREEN_SIZE { 1024 } else { SCREEN_SIZE };
+        if data.len() >= start + SCREEN_SIZE {
+            for (c, d) in screen.colors.iter_mut().zip(&data[start..start + SCREEN_SIZE]) {
+                *c = d & 0x0F;
+            }
+            if let Some(b) = data.get(start + SCREEN_SIZE) { screen.border = b & 0x0F }
+            if let Some(b) = data.get(start + SCREEN_SIZE + 1) { screen.background = b & 0x0F }
+        }
+        Ok(screen)
+    }
+    /// Load a screen from a C64 program file, which is the same as `load_from` but
+    /// preceded by a two byte load address.
+    pub fn load_prg_from<R: Read>(mut input: R) -> io::Result<Screen> {
+        let mut address = [0; 2];
+        input.read_exact(&mut address)?;
+        Screen::load_from(input)
+    }
+    /// Save the screen to some instance of `Write`, as 1000 bytes of screen RAM, 24 bytes of padding,
+    /// 1000 bytes of color RAM, and the border and background colors. This can be loaded by `load_from`.
+    pub fn save<W: Write>(&self, file: &mut W) -> io::Result<()> {
+        file.write_all(&self.codes)?;
+        file.write_all(&[0; 1024 - SCREEN_SIZE])?;
+        file.write_all(&self.colors)?;
+        file.write_all(&[self.border, self.background])
+    }
+    /// Save the screen as a C64 program file, which is the same as `save` but preceded by
+    /// the given load address (usually `0x0400`, the start of screen RAM).
+    pub fn save_prg<W: Write>(&self, file: &mut W, load_address: u16) -> io::Result<()> {
+        file.write_all(&load_address.to_le_bytes())?;
+        self.save(file)
+    }
+    /// Convert to a 40x25 graphic using the built-in PETscii tile set arrangement, with the C64's colors.
+    pub fn to_graphic(&self, charset: Charset) -> Graphic<()> {
+        let bg = C64_PALETTE[(self.background & 0x0F) as usize];
+        let mut g = Graphic::blank(WIDTH, HEIGHT);
+        for (i, (&code, &color)) in self.codes.iter().zip(&self.colors).enumerate().take(SCREEN_SIZE) {
+            let fg = C64_PALETTE[(color & 0x0F) as usize];
+            g.tiles[i] = if charset == Charset::Rom && code >= 0x80 {
+                Tile { index: tile_index(code & 0x7F), fg: bg, bg: fg }
+            } else {
+                Tile { index: tile_index(code), fg, bg }
+            };
+        }
+        g
+    }
+    /// Convert the top-left 40x25 tiles of a graphic to a screen, replacing each color with the nearest
+    /// of the C64's colors. The background color is the one used most, and as the C64 has only one
+    /// background color, any other backgrounds are lost (unless, with the `Rom` character set,
+    /// the tile can be shown in reverse video instead).
+    pub fn from_graphic<T>(graphic: &Graphic<T>, charset: Charset) -> Screen {
+        let palette = Swatch::from_colors(&C64_PALETTE);
+        let mut counts = HashMap::new();
+        for y in 0..HEIGHT.min(graphic.height) {
+            for x in 0..WIDTH.min(graphic.width) {
+                *counts.entry(palette.nearest(graphic.get_tile(x, y).bg, 16)).or_insert(0) += 1;
+            }
+        }
+        let background = counts.into_iter().max_by_key(|&(c, n)| (n, std::cmp::Reverse(c))).map_or(0, |(c, _)| c);
+        let mut screen = Screen { background: background as u8, border: background as u8, ..Screen::blank() };
+        for y in 0..HEIGHT {
+            for x in 0..WIDTH {
+                let i = (x + y * WIDTH) as usize;
+                let t = graphic.get_tile(x, y);
+                let (fg, bg) = (palette.nearest(t.fg, 16), palette.nearest(t.bg, 16));
+                let code = screen_code(t.index);
+                if charset == Charset::Rom && bg != background && fg == background {
+                    screen.codes[i] = code | 0x80;
+                    screen.colors[i] = bg as u8;
+                } else {
+                    screen.codes[i] = code;
+                    screen.colors[i] = fg as u8;
+                }
+            }
+        }
+        screen
+    }
+}
+
+impl Graphic<()> {
+    /// Load a C64 screen from the file at the given path as a graphic. Sugar for
+    /// `Screen::load_file` followed by `Screen::to_graphic`.
+    pub fn load_c64_file<P: AsRef<Path>>(path: P, charset: Charset) -> io::Result<Graphic<()>> {
+        Ok(Screen::load_file(path)?.to_graphic(charset))
+    }
+}
+
+/// Load a C64 character set from the file with the given path. Sugar for `load_charset_from` with `File::open`.
+pub fn load_charset_file<P: AsRef<Path>>(path: P) -> io::Result<TileSet> {
+    load_charset_from(File::open(path)?)
+}
+
+/// Load a C64 character set from a `Read` instance such as a file, in the arrangement of the
+/// built-in PETscii tile sets and with their character map. Accepts raw 2KB character sets
+/// and `.64c` files (which start with a two byte load address). A 4KB character set (both
+/// halves of the character ROM) fills the whole tile set.
+pub fn load_charset_from<R: Read>(mut input: R) -> io::Result<TileSet> {
+    let mut data = Vec::new();
+    input.read_to_end(&mut data)?;
+    let start = if data.len() % CHARSET_SIZE == 2 { 2 } else { 0 };
+    if data.len() < start + 8 {
+        return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "C64 character set is empty"));
+    }
+    let mut ts = TileSet::blank();
+    ts.char_map = TileSet::petscii().char_map;
+    for (code, glyph) in data[start..].chunks(8).enumerate().take(512) {
+        let mut tile = 0;
+        for (row, b) in glyph.iter().enumerate() {
+            tile |= (b.reverse_bits() as u64) << (row * 8);
+        }
+        ts[(code & 0x100) | tile_index(code as u8)] = tile;
+    }
+    Ok(ts)
+}
+
+/// Save the first 256 tiles of a tile set in the built-in PETscii arrangement as a raw 2KB C64 character set,
+/// preceded by the given load address if there is one (as in a `.64c` file).
+pub fn save_charset<W: Write>(tile_set: &TileSet, file: &mut W, load_address: Option<u16>) -> io::Result<()> {
+    if let Some(a) = load_address {
+        file.write_all(&a.to_le_bytes())?;
+    }
+    for code in 0..=255u8 {
+        let tile = tile_set[tile_index(code)];
+        for row in 0..8 {
+            file.write_all(&[((tile >> (row * 8)) as u8).reverse_bits()])?;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,83 @@
+//! The crate's own color type, so that tiles and graphics don't depend on SDL.
+
+use std::fmt;
+
+/// An RGBA color, with 8 bits per channel. Alpha 0 is fully transparent.
+///
+/// Its constructors match those of SDL's `Color`, and with the `sdl` feature it converts to and from it,
+/// so it can be passed straight to SDL functions that take colors.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Color {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+    pub a: u8,
+}
+
+impl Color {
+    /// An opaque color.
+    #[allow(non_snake_case)]
+    pub const fn RGB(r: u8, g: u8, b: u8) -> Color {
+        Color { r, g, b, a: 0xff }
+    }
+    /// A color with the given alpha.
+    #[allow(non_snake_case)]
+    pub const fn RGBA(r: u8, g: u8, b: u8, a: u8) -> Color {
+        Color { r, g, b, a }
+    }
+    /// The red, green and blue channels.
+    pub fn rgb(self) -> (u8, u8, u8) {
+        (self.r, self.g, self.b)
+    }
+    /// The red, green, blue and alpha channels.
+    pub fn rgba(self) -> (u8, u8, u8, u8) {
+        (self.r, self.g, self.b, self.a)
+    }
+    /// Parse a color written as `#rrggbb` or `#rrggbbaa`, as it is formatted by `Display`.
+    pub fn from_hex(s: &str) -> Option<Color> {
+        let hex = s.strip_prefix('#')?;
+        if !hex.bytes().all(|b| b.is_ascii_hexdigit()) || (hex.len() != 6 && hex.len() != 8) {
+            return None;
+        }
+        let channel = |i: usize| u8::from_str_radix(hex.get(i * 2..i * 2 + 2)?, 16).ok();
+        let a = if hex.len() == 8 { channel(3)? } else { 0xff };
+        Some(Color::RGBA(channel(0)?, channel(1)?, channel(2)?, a))
+    }
+}
+
+impl fmt::Display for Color {
+    /// Formats as `#rrggbb`, or `#rrggbbaa` if the color isn't opaque.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "#{:02x}{:02x}{:02x}", self.r, self.g, self.b)?;
+        if self.a != 0xff {
+            write!(f, "{:02x}", self.a)?;
+        }
+        Ok(())
+    }
+}
+
+impl From<(u8, u8, u8)> for Color {
+    fn from((r, g, b): (u8, u8, u8)) -> Color {
+        Color::RGB(r, g, b)
+    }
+}
+
+impl From<(u8, u8, u8, u8)> for Color {
+    fn from((r, g, b, a): (u8, u8, u8, u8)) -> Color {
+        Color::RGBA(r, g, b, a)
+    }
+}
+
+#[cfg(feature = "sdl")]
+impl From<sdl2::pixels::Color> for Color {
+    fn from(c: sdl2::pixels::Color) -> Color {
+        Color::RGBA(c.r, c.g, c.b, c.a)
+    }
+}
+
+#[cfg(feature = "sdl")]
+impl From<Color> for sdl2::pixels::Color {
+    fn from(c: Color) -> sdl2::pixels::Color {
+        sdl2::pixels::Color::RGBA(c.r, c.g, c.b, c.a)
+    }
+}
@@ -0,0 +1,127 @@
+//! A compressed version of the graphic file format, for graphics bundled into binaries.
+//!
+//! After a magic number and version, it stores the width and height, a table of the colors
+//! used, and then the tiles row by row as runs of identical tiles, each run giving its length,
+//! tile index and foreground and background color numbers. Numbers are stored as LEB128 varints,
+//! so most take a single byte.
+//!
+//! The magic number, read as the width of an uncompressed graphic, would be over a billion
+//! tiles wide, so `Graphic::load_from` can tell the two formats apart.
+
+use std::io;
+use std::io::{Read, Write};
+use std::collections::HashMap;
+
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+
+use super::{Color, Graphic, Tile};
+
+/// `tsrc` in the file, read as a little-endian `u32`.
+pub(crate) const MAGIC: u32 = u32::from_le_bytes(*b"tsrc");
+const VERSION: u8 = 1;
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+fn write_varint<W: Write>(file: &mut W, mut n: u64) -> io::Result<()> {
+    while n >= 0x80 {
+        file.write_u8((n as u8) | 0x80)?;
+        n >>= 7;
+    }
+    file.write_u8(n as u8)
+}
+
+fn read_varint<R: Read>(input: &mut R) -> io::Result<u64> {
+    let mut n = 0;
+    for shift in (0..64).step_by(7) {
+        let b = input.read_u8()?;
+        n |= ((b & 0x7F) as u64) << shift;
+        if b & 0x80 == 0 {
+            return Ok(n);
+        }
+    }
+    Err(invalid("number in compressed graphic is too long"))
+}
+
+impl<T> Graphic<T> {
+    /// Save a graphic to some instance of `Write` (such as a file) in the compressed format,
+    /// which `load_from` also loads. Graphics with large areas of the same tile, such as
+    /// backgrounds and screens of text, are usually a small fraction of the size of `save`'s.
+    pub fn save_compressed<W: Write>(&self, file: &mut W) -> io::Result<()> {
+        file.write_u32::<LittleEndian>(MAGIC)?;
+        file.write_u8(VERSION)?;
+        file.write_u32::<LittleEndian>(self.width)?;
+        file.write_u32::<LittleEndian>(self.height)?;
+        let mut colors = Vec::new();
+        let mut numbers = HashMap::new();
+        for t in &self.tiles {
+            for &c in [t.fg, t.bg].iter() {
+                numbers.entry(c).or_insert_with(|| {
+                    colors.push(c);
+                    colors.len() as u64 - 1
+                });
+            }
+        }
+        write_varint(file, colors.len() as u64)?;
+        for c in &colors {
+            file.write_all(&[c.r, c.g, c.b, c.a])?;
+        }
+        let mut tiles = self.tiles.iter().peekable();
+        while let Some(&t) = tiles.next() {
+            let mut run = 1;
+            while tiles.next_if(|&&u| u == t).is_some() {
+                run += 1;
+            }
+            write_varint(file, run)?;
+            write_varint(file, t.index as u64)?;
+            write_varint(file, numbers[&t.fg])?;
+            write_varint(file, numbers[&t.bg])?;
+        }
+        Ok(())
+    }
+}
+
+impl Graphic<()> {
+    /// Load the rest of a compressed graphic, after the magic number.
+    pub(crate) fn load_compressed_from<R: Read>(mut input: R) -> io::Result<Graphic<()>> {
+        if input.read_u8()? != VERSION {
+            return Err(invalid("unsupported compressed graphic version"));
+        }
+        let width = input.read_u32::<LittleEndian>()?;
+        let height = input.read_u32::<LittleEndian>()?;
+        let count = read_varint(&mut input)?;
+        let mut colors = Vec::new();
+        for _ in 0..count {
+            let mut c = [0; 4];
+            input.read_exact(&mut c)?;
+            colors.push(Color::RGBA(c[0], c[1], c[2], c[3]));
+        }
+        let color = |n: u64| colors.get(n as usize).copied().ok_or_else(|| invalid("color number out of range in compressed graphic"));
+        let size = (width as usize).checked_mul(height as usize).ok_or_else(|| invalid("compressed graphic is too large"))?;
+        // The runs are read before the graphic is made, so a file claiming to be huge
+        // runs out of data rather than making an enormous graphic first.
+        let mut runs = Vec::new();
+        let mut cur = 0;
+        while cur < size {
+            let run = read_varint(&mut input)? as usize;
+            let index = read_varint(&mut input)? as usize;
+            let fg = color(read_varint(&mut input)?)?;
+            let bg = color(read_varint(&mut input)?)?;
+            if run == 0 || run > size - cur {
+                return Err(invalid("run out of range in compressed graphic"));
+            }
+            runs.push((run, Tile { index, fg, bg }));
+            cur += run;
+        }
+        let mut g = Graphic::blank(width, height);
+        let mut cur = 0;
+        for (run, tile) in runs {
+            for t in &mut g.tiles[cur..cur + run] {
+                *t = tile;
+            }
+            cur += run;
+        }
+        Ok(g)
+    }
+}
@@ -2,30 +2,101 @@
 //! 8x8 pixel two-color tiles, in the spirit of text mode drawing in early 8-bit
 //! computers, using SDL.
 //! 
+//! Drawing with SDL is behind the `sdl` cargo feature, which is on by default. Without it,
+//! graphics can still be loaded, edited, saved and drawn with other backends such as the terminal.
+//! 
 //! It includes file formats for saving and loading tile sets and graphics,
 //! as well as a number of built-in tile sets.
 //! 
+//! The `semigraphics` module allows plotting pixels smaller than a tile
+//! using the block graphics characters included in many tile sets.
+//! The `autotile` module paints terrain onto graphics, choosing edge and
+//! corner tiles for it automatically.
+//! 
+//! As well as SDL, graphics can be drawn to a terminal using ANSI escape
+//! sequences with the `terminal` module, or as Sixel images with the `sixel` module.
+//! 
+//! The `ansi` module supports importing and exporting ANSI art files as drawn
+//! for BBSes, and the `xbin` module supports XBin files, which bundle a font
+//! and palette with the art. The `c64` module loads and saves Commodore 64
+//! screens and character sets, the `spectrum` module ZX Spectrum screens,
+//! and the `teletext` module teletext pages.
+//! 
+//! The `bundle` module keeps a tile set and swatch together with the graphics
+//! drawn with them, so that graphics are always shown with the right ones.
+//! The `stamp` module keeps libraries of stamps, small graphics that are
+//! placed as a unit to build larger ones.
+//! 
+//! The `tiled` module exports tile sets and graphics to the Tiled map editor
+//! and imports maps drawn with it.
+//! 
+//! The `ui` module has widgets such as buttons, lists and text inputs, drawn
+//! into graphics, for building menus and dialogs.
+//! 
+//! The `text` module adds a plain text format for tile sets and graphics,
+//! for reviewing changes to them in version control.
+//! 
+//! With the `rexpaint` feature, the `rexpaint` module loads and saves images
+//! made with the REXPaint ASCII art editor, including their layers.
+//! 
+//! With the `serde` feature, tiles, graphics, tile sets, colors and swatches can be
+//! serialized with serde, to store them in formats like RON or JSON.
+//! 
 //! It also includes a tile and graphics editor, itself made with tesserae,
 //! called `tesseraed`.
 //! 
 //! `tesseraed` is stored as an example in the library, and can be run with
 //! `cargo run --example tesseraed`.
 
+#[cfg(feature = "sdl")]
 extern crate sdl2;
 extern crate byteorder;
 
+pub mod ansi;
+pub mod autotile;
+pub mod bundle;
+pub mod c64;
+pub mod cp437;
+#[cfg(feature = "rexpaint")]
+pub mod rexpaint;
+pub mod semigraphics;
+pub mod sixel;
+pub mod spectrum;
+pub mod stamp;
+pub mod teletext;
+pub mod terminal;
+pub mod text;
+pub mod tiled;
+pub mod ui;
+pub mod xbin;
+mod backend;
+mod color;
+mod compressed;
+mod properties;
+#[cfg(feature = "sdl")]
+mod sdl;
+#[cfg(feature = "serde")]
+mod serialize;
+mod swatch;
+mod version;
+
+pub use backend::{Backend, Draw, Region};
+pub use color::Color;
+pub use properties::TileProperties;
+#[cfg(feature = "sdl")]
+pub use sdl::{draw_tile_data, GlyphAtlas, TileCache, TileCacheStats};
+pub use swatch::Swatch;
+pub use version::TileSetVersion;
+
+use std::collections::BTreeMap;
 use std::fs::File;
 use std::path::Path;
 use std::io::{Cursor,Read,Write};
 use std::io;
 use std::ops::{Index,IndexMut};
-use std::collections::HashMap;
 
 use byteorder::{LittleEndian,ReadBytesExt,WriteBytesExt};
 
-use sdl2::pixels::{Color,PixelFormatEnum};
-use sdl2::render::{Texture,TextureCreator,BlendMode,Canvas, RenderTarget};
-use sdl2::rect::{Point,Rect};
 
 const TILESET_SIZE : usize = 512;
 
@@ -138,10 +209,36 @@ const fn create_character_map () -> [usize;256] {
 ///
 /// Can also be indexed by `char`, which gives the tile index (a `usize`) corresponding to that particular character.
 /// The character map can be changed by assigning to a particular `char` index.
-#[derive(Clone)]
+/// 
+/// Each change made through indexing is recorded in the tile set's `version`, so that 
+/// graphics drawn with it redraw the tiles that changed on their next `update_texture`.
+///
+/// Each tile can also have `TileProperties`, such as a name and whether it is solid,
+/// which are saved with the tile set.
 pub struct TileSet {
     data: Vec<u64>,
-    char_map: [usize;256]
+    char_map: [usize;256],
+    properties: BTreeMap<usize, TileProperties>,
+    id: u64,
+    revision: u64,
+    tile_revisions: Vec<u64>,
+    char_map_revision: u64,
+}
+
+impl Clone for TileSet {
+    /// A copy of the tile set. The copy is a different tile set as far as versions are concerned,
+    /// as the two may be changed separately.
+    fn clone(&self) -> TileSet {
+        TileSet {
+            data: self.data.clone(),
+            char_map: self.char_map,
+            properties: self.properties.clone(),
+            id: version::next_id(),
+            revision: self.revision,
+            tile_revisions: self.tile_revisions.clone(),
+            char_map_revision: self.char_map_revision,
+        }
+    }
 }
 
 impl TileSet { 
@@ -149,7 +246,12 @@ impl TileSet {
     fn new() -> TileSet {
         TileSet {
             data: Vec::new(),
-            char_map: CHAR_MAP
+            char_map: CHAR_MAP,
+            properties: BTreeMap::new(),
+            id: version::next_id(),
+            revision: 0,
+            tile_revisions: Vec::new(),
+            char_map_revision: 0,
         }
     }
     /// Create a blank tile set with 512 tiles (all pixels off) and the default character map.
@@ -163,14 +265,16 @@ impl TileSet {
     /// Load a tile set from the file with the given path. Sugar for `load_from` with `File::open`.
     pub fn load_file<P: AsRef<Path>>(path : P) -> io::Result<TileSet> {
         let f = File::open(path)?;
-        Ok(TileSet::load_from(f))
+        TileSet::load_from(f)
     }
     /// Load a tile set from a `Read` instance such as a file. Commonly used with `include_bin!` like so: 
     /// ```
     /// let ts = include_bytes!("tile_set");
-    /// TileSet::load_from(Cursor::new(&ts[..]))
+    /// TileSet::load_from(Cursor::new(&ts[..]))?
     /// ```
-    pub fn load_from<R: Read>(mut input : R) -> TileSet {
+    /// Files that end early are filled out with blank tiles and the default character map,
+    /// but a damaged tile properties section is an error.
+    pub fn load_from<R: Read>(mut input : R) -> io::Result<TileSet> {
         let mut ts = TileSet::new();
         let mut c = 0;
         while c < TILESET_SIZE {
@@ -191,42 +295,54 @@ impl TileSet {
             }
             c += 1;
         }
-        ts
+        let mut properties = TileSet::load_properties(input)?;
+        properties.retain(|&i, _| i < ts.len());
+        ts.properties = properties;
+        Ok(ts)
     }
     /// A built-in tile set used in, among other things, the tesseraed editor.
     pub fn default() -> TileSet {
         let ts = include_bytes!("../tile_set");
-        TileSet::load_from(Cursor::new(&ts[..]))
+        TileSet::load_from(Cursor::new(&ts[..])).unwrap()
     }
     /// A built-in tile set containing the 256 CGA standard ASCII characters using 
     /// the font used in Hercules graphics cards.
+    /// Each character is stored at the tile index of its code page 437 code,
+    /// and the character map covers all 256 codes.
     pub fn cga_ascii() -> TileSet {
         let ts = include_bytes!("../cga");
-        TileSet::load_from(Cursor::new(&ts[..]))
+        let mut ts = TileSet::load_from(Cursor::new(&ts[..])).unwrap();
+        for i in 0..256 {
+            ts.char_map[i] = i;
+        }
+        ts
     }
     /// The regular, all-uppercase shifted PETscii tile set used in 
     /// Commodore PET and Commodore 64 machines.
     pub fn petscii() -> TileSet {
         let ts = include_bytes!("../petscii");
-        TileSet::load_from(Cursor::new(&ts[..]))
+        TileSet::load_from(Cursor::new(&ts[..])).unwrap()
     }
     /// The unshifted PETscii tile set which includes lower case letters 
     /// used in Commodore PET and Commodore 64 machines.
     pub fn petscii_unshifted() -> TileSet {
         let ts = include_bytes!("../petscii_unshifted");
-        TileSet::load_from(Cursor::new(&ts[..]))
+        TileSet::load_from(Cursor::new(&ts[..])).unwrap()
     }
 
-    /// Save the tileset to a file at the provided path.
-    pub fn store<P: AsRef<Path>>(&self,path : P ) -> io::Result<()> {
-        let mut f = File::create(path)?;
+    /// Save the tileset to some instance of `Write` (such as a file), using the same file format used in `load_from`.
+    pub fn save<W: Write>(&self, file: &mut W) -> io::Result<()> {
         for i in &self.data {
-            f.write_u64::<LittleEndian>(*i)?;
+            file.write_u64::<LittleEndian>(*i)?;
         }
         for i in 0..256 {
-            f.write_u16::<LittleEndian>(self.char_map[i] as u16)?;
+            file.write_u16::<LittleEndian>(self.char_map[i] as u16)?;
         }
-        Ok(())
+        self.save_properties(file)
+    }
+    /// Save the tileset to a file at the provided path.
+    pub fn store<P: AsRef<Path>>(&self,path : P ) -> io::Result<()> {
+        self.save(&mut File::create(path)?)
     }
 
     /// Should always return 512, but using this gives you future-proofing 
@@ -234,10 +350,28 @@ impl TileSet {
     pub fn len(&self) -> usize {
         self.data.len()
     }   
-    
-    fn draw_tile_to<P : Into<Point>>(&self, index: usize , tex: &mut Texture, point: P, fg: Color, bg: Color) {        
-        draw_tile_data(self.data[index],tex,point, fg, bg)
+
+    /// The reverse of the character map: for each tile index, the character that maps to it, if any.
+    /// Where several characters map to the same tile, printable ASCII characters are preferred.
+    /// The tile that most characters map to (usually tile 0, for characters the tile set 
+    /// doesn't include) is taken to have no character, unless space maps to it.
+    pub fn reverse_char_map(&self) -> Vec<Option<char>> {
+        let mut counts = vec![0; self.len()];
+        for &i in self.char_map.iter() {
+            if i < counts.len() { counts[i] += 1 }
+        }
+        let fallback = (0..counts.len()).max_by_key(|&i| (counts[i], std::cmp::Reverse(i))).filter(|&i| counts[i] > 1);
+        let mut chars = vec![None; self.len()];
+        let order = (0x20..0x7F).chain(0x80..0x100).chain(0x01..0x20).chain(vec![0x7F, 0x00]);
+        for code in order {
+            let i = self.char_map[code];
+            if i < chars.len() && chars[i].is_none() && (Some(i) != fallback || code == 0x20) {
+                chars[i] = Some(code as u8 as char);
+            }
+        }
+        chars
     }
+
 }
 impl Index<char> for TileSet {
     type Output = usize;
@@ -247,6 +381,7 @@ impl Index<char> for TileSet {
 }
 impl IndexMut<char> for TileSet {
     fn index_mut(&mut self,index:char) -> &mut usize {
+        self.char_map_changed();
         &mut self.char_map[index as usize]
     }
 }
@@ -258,6 +393,7 @@ impl Index<usize> for TileSet {
 }
 impl IndexMut<usize> for TileSet {
     fn index_mut(&mut self, index: usize) -> &mut u64 {
+        self.tile_changed(index);
         &mut self.data[index]
     }
 }
@@ -289,7 +425,14 @@ impl Default for Tile {
 /// Which tiles have been drawn to the texture are stored in a special 
 /// cache. Whenever `update_texture` is called, the tiles that have been 
 /// changed relative to that cache are redrawn to the texture.
-/// The cache can be entirely invalidated by calling `mark_dirty`.
+/// The cache can be entirely invalidated by calling `mark_dirty`, though 
+/// changes to the tile set, or using a different one, are noticed without it.
+/// 
+/// Changing tiles records the region of the graphic that changed, so 
+/// `update_texture` only looks at those regions rather than every tile.
+/// 
+/// More generally, `T` can be any `Backend`, including `TileCache`, `GlyphAtlas`,
+/// the terminal backend in the `terminal` module, or your own.
 #[derive(Clone)]
 pub struct Graphic<T> {
     width: u32, 
@@ -297,6 +440,8 @@ pub struct Graphic<T> {
     tiles: Vec<Tile>,
     texture: T,
     dirty: Vec<Option<Tile>>,
+    damage: Vec<Region>,
+    tile_set_version: Option<TileSetVersion>,
 }
 
 impl <T> Index<(u32, u32)> for Graphic<T> {
@@ -307,6 +452,7 @@ impl <T> Index<(u32, u32)> for Graphic<T> {
 }
 impl <T> IndexMut<(u32, u32)> for Graphic<T> {
     fn index_mut(&mut self, index : (u32,u32)) -> &mut Tile {
+        self.add_damage(index.0, index.1, 1, 1);
         &mut self.tiles[(index.0 + index.1 * self.width) as usize]
     }
 }
@@ -324,7 +470,8 @@ impl Graphic<()> {
     }
     /// Load a graphic from any instance of the `Read` trait.
     /// Commonly used for loading files statically bundled into the binary with
-    /// `include_bytes!`.
+    /// `include_bytes!`. Graphics saved with `save_compressed` are detected and
+    /// decompressed.
     /// 
     /// ```
     /// let g = Graphic::load_from(Cursor::new(&include_bytes!("file")[..]))
@@ -332,6 +479,9 @@ impl Graphic<()> {
     pub fn load_from<R: Read>(input : R) -> io::Result<Graphic<()>> {
         let mut f = input;
         let w = f.read_u32::<LittleEndian>()?;
+        if w == compressed::MAGIC {
+            return Graphic::load_compressed_from(f);
+        }
         let h = f.read_u32::<LittleEndian>()?;
         let mut me = Graphic::blank(w,h);
         let mut cur = 0;
@@ -363,7 +513,9 @@ impl Graphic<()> {
             height: height,
             tiles: tiles,
             texture: (),
-            dirty: dirty
+            dirty: dirty,
+            damage: vec![Region { x: 0, y: 0, width, height }],
+            tile_set_version: None
         }
     }
     /// Create a blank graphic of the given size, with the tile index 0, fully transparent colors.
@@ -376,41 +528,19 @@ impl Graphic<()> {
         Graphic::solid(width,height,Default::default())
     }
 
-    /// A method to attach an SDL texture, converting the graphic from an unrenderable one to a renderable one.
-    /// Commonly used straight after creation, like so:
-    /// ```
-    /// let g = Graphics::load_file("path")?.textured(texture_creator);
-    /// ```
-    /// Note that the texture has not rendered yet, so typically you would want to call `update_texture` 
-    /// and provide a tileset before drawing to screen.
-    pub fn textured<'r, T>(&self,texture_creator: &'r TextureCreator<T>) -> Graphic<Texture<'r>> {
-        let mut tex = texture_creator.create_texture_streaming(PixelFormatEnum::ARGB8888, 8 * self.width, 8 * self.height).unwrap();
-        tex.set_blend_mode(BlendMode::Blend);
-        let g = Graphic {
-            width: self.width,
-            height: self.height,
-            tiles: self.tiles.clone(),
-            texture: tex,
-            dirty: self.dirty.clone()
-        };
-        g
-    }
-    /// A method to attach a hash table of individual tile textures, converting the graphic from an unrenderable one to a renderable one.
-    /// Commonly used straight after creation, like so:
-    /// ```
-    /// let g = Graphics::load_file("path")?.tile_cache_textured(texture_creator);
-    /// ```
-    /// Note that the texture has not rendered yet, so typically you would want to call `update_texture` 
-    /// and provide a tileset before drawing to screen.
-    pub fn tile_cache_textured<'r, T>(&self,texture_creator: &'r TextureCreator<T>) -> Graphic<TileCache<'r,T>> {
-        let g = Graphic {
+    /// Attach any `Backend`, converting the graphic from an unrenderable one to one rendered by that backend.
+    /// `textured`, `tile_cache_textured` and `terminal_textured` are shortcuts for the built-in backends.
+    /// The backend is given every tile on the next call to `update_texture`.
+    pub fn with_backend<B: Backend>(&self, backend: B) -> Graphic<B> {
+        Graphic {
             width: self.width,
             height: self.height,
             tiles: self.tiles.clone(),
-            texture: TileCache { cache: HashMap::new(), texture_creator: texture_creator },
-            dirty: self.dirty.clone()
-        };
-        g
+            texture: backend,
+            dirty: vec![None; self.dirty.len()],
+            damage: vec![Region { x: 0, y: 0, width: self.width, height: self.height }],
+            tile_set_version: None
+        }
     }
 }
 impl <T>Graphic<T> {
@@ -460,6 +590,7 @@ impl <T>Graphic<T> {
             if let Some(t) = self.tiles.get_mut(i) {
                 *t = tile;
             }
+            self.add_damage(x, y, 1, 1);
         }
     }
     /// Set the colors of the given tile in the graphic but leave the tile index unchanged.
@@ -470,6 +601,7 @@ impl <T>Graphic<T> {
                 t.fg = fg;
                 t.bg = bg;
             }
+            self.add_damage(x, y, 1, 1);
         }
 
     }
@@ -530,110 +662,4 @@ impl <T>Graphic<T> {
 
 }
 
-impl <'r>Graphic<Texture<'r>> {
 
-    /// A shortcut to load a file and associate a texture in one step. Equivalent to using `load_file` and then `textured`.
-    pub fn load_file_textured<P: AsRef<Path>,T>(path : P,texture_creator: &'r TextureCreator<T>) -> io::Result<Graphic<Texture<'r>>> {
-        let g = Graphic::load_from(File::open(path)?)?;
-        Ok(g.textured(texture_creator))
-    }
-    /// Instructs the next invocation of update_texture to redraw all tiles, regardless of whether it thinks they need redrawing.
-    pub fn mark_dirty(&mut self) {
-        for i in 0..self.dirty.len() {
-            self.dirty[i] = None
-        }
-    }
-    /// Draw each tile that needs redrawing in the graphic to the internal texture using the provided tile set.
-    /// Returns number of tiles redrawn.
-    pub fn update_texture(&mut self, tile_set : &TileSet) -> u32 {
-        let mut i = 0;
-        let mut c = 0;
-        for y in 0..self.height {
-            for x in 0..self.width {
-                let t = self.tiles[i];
-                if self.dirty[i] != Some(t) {
-                    tile_set.draw_tile_to(t.index,&mut self.texture,Point::new((x * 8) as i32, (y * 8) as i32), t.fg,t.bg);
-                    self.dirty[i] = Some(t);
-                    c += 1;
-                }
-                i += 1
-            }
-        }
-        c
-    }
-    /// Draw the graphic to the screen at the provided position.
-    /// Note that you may wish to call `update_texture` and provide a tile set first, as this simply draws the 
-    /// cached texture.
-    pub fn draw<P : Into<Point>,T:RenderTarget>(&self, canvas: &mut Canvas<T>, position : P) {   
-        let position = position.into();
-        canvas.copy(&self.texture, None, Rect::new(position.x,position.y,self.width * 8, self.height * 8)).unwrap();
-    }
-    /// Get the SDL texture associated with this graphic.
-    pub fn texture(&self) -> &Texture {
-        &self.texture
-    }
-}
-
-/// Mostly used internally. 
-/// Given a 64 bit integer, interprets it as an 8x8 tile and draws it to the 
-/// given texture at the given point with the given foreground and background colors.
-pub fn draw_tile_data<P : Into<Point>>(data: u64 , tex: &mut Texture, point: P, fg: Color, bg: Color) {
-        let point = point.into();
-        let mut m = data;
-        let mut pixel_data = [0 as u8; (64 * 4)];        
-        let mut curr = 0;
-        for _ in 0..64 {
-            let (r,g,b,a) = if (m & 0x01) != 0 { fg.rgba() } else { bg.rgba() };
-            m >>= 1;
-            pixel_data[curr] = b; curr += 1;
-            pixel_data[curr] = g; curr += 1;
-            pixel_data[curr] = r; curr += 1;
-            pixel_data[curr] = a; curr += 1;
-        }
-        tex.update(Rect::new(point.x,point.y,8,8),&pixel_data, 8 * 4).unwrap();
-}
-
-
-/// An alternative backend that stores each individual tile as a separate SDL texture in a hashtable.
-/// Can be used in some cases as a drop-in replacement for a textured graphic.
-/// Draw is slightly slower but updating textures is significantly faster if the same 
-/// tile is used in a lot of different places.
-pub struct TileCache<'r,C> {
-    texture_creator: &'r TextureCreator<C>,
-    cache: HashMap<Tile,Texture<'r>>
-}
-
-impl <'r,C>Graphic<TileCache<'r,C>> {
-
-    /// Draw each tile that needs redrawing in the graphic to the internal texture cache using the provided tile set.
-    /// Returns number of tiles redrawn.
-    pub fn update_texture(&mut self, tile_set : &TileSet) -> u32 {
-        let mut c = 0;
-        for t in &self.tiles {
-            if !self.texture.cache.contains_key(t) {
-                let mut tex = self.texture.texture_creator.create_texture_streaming(PixelFormatEnum::ARGB8888, 8, 8).unwrap();
-                tex.set_blend_mode(BlendMode::Blend);
-                tile_set.draw_tile_to(t.index, &mut tex, (0,0), t.fg, t.bg);
-                self.texture.cache.insert(*t,tex);
-                c += 1;
-            }
-        }
-        c
-    }
-    /// Draw the graphic to the screen at the provided position.
-    /// Note that you may wish to call `update_texture` and provide a tile set first, as this simply draws the 
-    /// cached textures.
-    pub fn draw<P : Into<Point>,T:RenderTarget>(&self, canvas: &mut Canvas<T>, position : P) {   
-        let mut i = 0;
-        let pos = position.into();
-        for y in 0..self.height {
-            for x in 0..self.width {
-                let t = self.tiles[i];
-                if let Some(tex) = self.texture.cache.get(&t) {
-                    canvas.copy(&tex,None,Rect::new(pos.x + x as i32 * 8, pos.y + y as i32 * 8, 8 ,8)).unwrap();
-                }
-                i += 1
-            }
-        }
-    }
-}
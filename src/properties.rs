@@ -0,0 +1,154 @@
+//! Information about what tiles are, such as names, tags and whether they are solid,
+//! kept in the tile set with the tiles so it moves with them.
+
+use std::io;
+use std::io::{Read, Write};
+use std::collections::{BTreeMap, BTreeSet};
+
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+
+use super::bundle::{read_string, write_section};
+use super::TileSet;
+
+/// Marks the properties section after the character map in a tile set file.
+const MAGIC: &[u8; 4] = b"tsrp";
+const VERSION: u8 = 1;
+
+/// What a game or tool knows about a tile beyond its pixels. Everything is empty (and the tile
+/// passable) unless set.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(default))]
+pub struct TileProperties {
+    pub name: String,
+    pub tags: BTreeSet<String>,
+    /// Whether the tile blocks movement, such as a wall. Tiles that aren't solid are passable.
+    pub solid: bool,
+    /// Any other properties, by key.
+    pub values: BTreeMap<String, String>,
+}
+
+impl TileProperties {
+    /// Whether nothing has been set.
+    pub fn is_empty(&self) -> bool {
+        *self == TileProperties::default()
+    }
+    /// Whether the tile has the given tag.
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.contains(tag)
+    }
+    /// The value of the property with the given key, if it has been set.
+    pub fn value(&self, key: &str) -> Option<&str> {
+        self.values.get(key).map(|v| v.as_str())
+    }
+}
+
+impl TileSet {
+    /// The properties of the tile with the given index, if any have been set.
+    pub fn tile_properties(&self, index: usize) -> Option<&TileProperties> {
+        self.properties.get(&index)
+    }
+    /// The properties of the tile with the given index, for changing them.
+    /// Panics if the index is out of range, as indexing does.
+    pub fn tile_properties_mut(&mut self, index: usize) -> &mut TileProperties {
+        assert!(index < self.data.len(), "tile index {} out of range", index);
+        self.properties.entry(index).or_default()
+    }
+    /// Replace the properties of the tile with the given index, returning the old ones, if any had been set.
+    pub fn set_tile_properties(&mut self, index: usize, properties: TileProperties) -> Option<TileProperties> {
+        if properties.is_empty() {
+            self.properties.remove(&index)
+        } else {
+            self.tile_properties_mut(index);
+            self.properties.insert(index, properties)
+        }
+    }
+    /// Whether the tile with the given index is solid. Shorthand for checking its properties.
+    pub fn is_solid(&self, index: usize) -> bool {
+        self.properties.get(&index).is_some_and(|p| p.solid)
+    }
+    /// Whether the tile with the given index has the given tag.
+    pub fn has_tag(&self, index: usize, tag: &str) -> bool {
+        self.properties.get(&index).is_some_and(|p| p.has_tag(tag))
+    }
+    /// The index of the first tile with the given name, if any.
+    pub fn tile_named(&self, name: &str) -> Option<usize> {
+        self.properties.iter().find(|(_, p)| p.name == name).map(|(&i, _)| i)
+    }
+    /// The indices of the tiles with the given tag, in order.
+    pub fn tiles_tagged(&self, tag: &str) -> Vec<usize> {
+        self.properties.iter().filter(|(_, p)| p.has_tag(tag)).map(|(&i, _)| i).collect()
+    }
+    /// Swap two tiles, along with their properties and the characters mapped to them,
+    /// so that reordering a tile set doesn't separate tiles from what is known about them.
+    pub fn swap_tiles(&mut self, a: usize, b: usize) {
+        if a == b {
+            return;
+        }
+        let data = self[a];
+        self[a] = self[b];
+        self[b] = data;
+        let (pa, pb) = (self.properties.remove(&a), self.properties.remove(&b));
+        self.properties.extend(pa.map(|p| (b, p)));
+        self.properties.extend(pb.map(|p| (a, p)));
+        for code in 0..256 {
+            let c = code as u8 as char;
+            if self[c] == a {
+                self[c] = b;
+            } else if self[c] == b {
+                self[c] = a;
+            }
+        }
+    }
+    /// Write the properties section, if any tile has properties.
+    pub(crate) fn save_properties<W: Write>(&self, file: &mut W) -> io::Result<()> {
+        let properties: Vec<_> = self.properties.iter().filter(|(_, p)| !p.is_empty()).collect();
+        if properties.is_empty() {
+            return Ok(());
+        }
+        file.write_all(MAGIC)?;
+        file.write_u8(VERSION)?;
+        file.write_u32::<LittleEndian>(properties.len() as u32)?;
+        for (&index, p) in properties {
+            file.write_u16::<LittleEndian>(index as u16)?;
+            write_section(file, p.name.as_bytes())?;
+            file.write_u32::<LittleEndian>(p.tags.len() as u32)?;
+            for tag in &p.tags {
+                write_section(file, tag.as_bytes())?;
+            }
+            file.write_u8(p.solid as u8)?;
+            file.write_u32::<LittleEndian>(p.values.len() as u32)?;
+            for (key, value) in &p.values {
+                write_section(file, key.as_bytes())?;
+                write_section(file, value.as_bytes())?;
+            }
+        }
+        Ok(())
+    }
+    /// Read the properties section that may follow the character map. Tile sets without one,
+    /// from before tiles had properties, have none.
+    pub(crate) fn load_properties<R: Read>(mut input: R) -> io::Result<BTreeMap<usize, TileProperties>> {
+        let mut properties = BTreeMap::new();
+        let mut magic = [0; 4];
+        if input.read(&mut magic[..1])? == 0 {
+            return Ok(properties);
+        }
+        input.read_exact(&mut magic[1..])?;
+        if &magic != MAGIC || input.read_u8()? != VERSION {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "unsupported tile properties"));
+        }
+        for _ in 0..input.read_u32::<LittleEndian>()? {
+            let index = input.read_u16::<LittleEndian>()? as usize;
+            let mut p = TileProperties { name: read_string(&mut input)?, ..TileProperties::default() };
+            for _ in 0..input.read_u32::<LittleEndian>()? {
+                p.tags.insert(read_string(&mut input)?);
+            }
+            p.solid = input.read_u8()? != 0;
+            for _ in 0..input.read_u32::<LittleEndian>()? {
+                let key = read_string(&mut input)?;
+                p.values.insert(key, read_string(&mut input)?);
+            }
+            properties.insert(index, p);
+        }
+        Ok(properties)
+    }
+}
@@ -0,0 +1,159 @@
+//! Loading and saving REXPaint images (`.xp`), behind the `rexpaint` feature.
+//!
+//! REXPaint is an editor for ASCII art, popular for roguelikes. Its images are made of layers
+//! of cells, each a code page 437 character with a foreground and background color, which map
+//! directly onto graphics drawn with `TileSet::cga_ascii`, where each character is at the tile
+//! index of its code. Codes of 256 or more are kept as they are, so graphics using all 512 tiles
+//! survive a round trip.
+//!
+//! REXPaint marks cells of layers that are see-through with a magenta (`#ff00ff`) background.
+//! These become blank tiles with fully transparent colors, and fully transparent backgrounds
+//! are saved as magenta.
+//!
+//! ```ignore
+//! let image = XpImage::load_file("map.xp")?;
+//! let map = image.flatten().atlas_textured(&texture_creator);
+//! ```
+
+use std::fs::File;
+use std::io;
+use std::io::{Read, Write};
+use std::path::Path;
+
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+
+use super::{Color, Graphic, Tile};
+
+/// The version number REXPaint writes at the start of its files.
+const VERSION: i32 = -1;
+/// The background REXPaint uses for cells that lower layers show through.
+const TRANSPARENT: (u8, u8, u8) = (255, 0, 255);
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+fn read_color<R: Read>(input: &mut R, alpha: u8) -> io::Result<Color> {
+    let mut c = [0; 3];
+    input.read_exact(&mut c)?;
+    Ok(Color::RGBA(c[0], c[1], c[2], alpha))
+}
+
+/// The contents of a REXPaint file: its layers, from the bottom up, all the same size.
+#[derive(Clone)]
+pub struct XpImage {
+    pub layers: Vec<Graphic<()>>,
+}
+
+impl XpImage {
+    /// An image with a single layer, a copy of the graphic.
+    pub fn from_graphic<T>(graphic: &Graphic<T>) -> XpImage {
+        let mut g = Graphic::blank(graphic.width, graphic.height);
+        g.copy_all_tiles_from(graphic, 0, 0);
+        XpImage { layers: vec![g] }
+    }
+    /// The layers drawn on top of each other, as REXPaint shows them: cells of upper layers with a
+    /// transparent background let the cells of the layers below show through.
+    pub fn flatten(&self) -> Graphic<()> {
+        let (width, height) = self.layers.first().map_or((0, 0), |l| (l.width, l.height));
+        let mut g = Graphic::blank(width, height);
+        for layer in &self.layers {
+            for (t, &u) in g.tiles.iter_mut().zip(layer.tiles.iter()) {
+                if u.bg.a != 0 {
+                    *t = u;
+                }
+            }
+        }
+        g
+    }
+    /// Load a REXPaint file from the given path. Sugar for `load_from` with `File::open`.
+    pub fn load_file<P: AsRef<Path>>(path: P) -> io::Result<XpImage> {
+        XpImage::load_from(File::open(path)?)
+    }
+    /// Load a REXPaint file from any instance of the `Read` trait.
+    pub fn load_from<R: Read>(input: R) -> io::Result<XpImage> {
+        let mut input = GzDecoder::new(input);
+        // Files from before REXPaint had version numbers start with the layer count instead.
+        let mut count = input.read_i32::<LittleEndian>()?;
+        if count < 0 {
+            count = input.read_i32::<LittleEndian>()?;
+        }
+        if count < 1 {
+            return Err(invalid("REXPaint image has no layers"));
+        }
+        let mut layers: Vec<Graphic<()>> = Vec::new();
+        for _ in 0..count {
+            let width = input.read_i32::<LittleEndian>()?;
+            let height = input.read_i32::<LittleEndian>()?;
+            if width < 0 || height < 0 {
+                return Err(invalid("REXPaint layer has a negative size"));
+            }
+            if layers.first().is_some_and(|l| (l.width, l.height) != (width as u32, height as u32)) {
+                return Err(invalid("REXPaint layers are different sizes"));
+            }
+            let size = (width as usize).checked_mul(height as usize).ok_or_else(|| invalid("REXPaint layer is too large"))?;
+            // The cells are read before the graphic is made, so a file claiming to be huge
+            // runs out of data rather than making an enormous graphic first.
+            let mut cells = Vec::new();
+            for _ in 0..size {
+                let index = input.read_u32::<LittleEndian>()? as usize;
+                let fg = read_color(&mut input, 255)?;
+                let bg = read_color(&mut input, 255)?;
+                cells.push(Tile { index, fg, bg });
+            }
+            let mut g = Graphic::blank(width as u32, height as u32);
+            // Cells are stored column by column.
+            for (i, &t) in cells.iter().enumerate() {
+                if (t.bg.r, t.bg.g, t.bg.b) != TRANSPARENT {
+                    g.set_tile((i / height as usize) as u32, (i % height as usize) as u32, t);
+                }
+            }
+            layers.push(g);
+        }
+        // Reading to the end checks the gzip trailer, so damaged files aren't loaded.
+        input.read_to_end(&mut Vec::new())?;
+        Ok(XpImage { layers })
+    }
+    /// Save as a REXPaint file to some instance of `Write` (such as a file).
+    /// Colors are saved without their alpha, except that fully transparent backgrounds are saved as magenta.
+    pub fn save<W: Write>(&self, file: &mut W) -> io::Result<()> {
+        let mut out = GzEncoder::new(file, Compression::default());
+        out.write_i32::<LittleEndian>(VERSION)?;
+        out.write_i32::<LittleEndian>(self.layers.len() as i32)?;
+        for layer in &self.layers {
+            out.write_i32::<LittleEndian>(layer.width as i32)?;
+            out.write_i32::<LittleEndian>(layer.height as i32)?;
+            for x in 0..layer.width {
+                for y in 0..layer.height {
+                    let t = layer.get_tile(x, y);
+                    let bg = if t.bg.a == 0 { TRANSPARENT } else { (t.bg.r, t.bg.g, t.bg.b) };
+                    out.write_u32::<LittleEndian>(t.index as u32)?;
+                    out.write_all(&[t.fg.r, t.fg.g, t.fg.b, bg.0, bg.1, bg.2])?;
+                }
+            }
+        }
+        out.finish()?;
+        Ok(())
+    }
+    /// Save as a REXPaint file at the provided path.
+    pub fn store<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        self.save(&mut File::create(path)?)
+    }
+}
+
+impl Graphic<()> {
+    /// Load a REXPaint file from the given path, with its layers flattened into one graphic.
+    pub fn load_xp_file<P: AsRef<Path>>(path: P) -> io::Result<Graphic<()>> {
+        Ok(XpImage::load_file(path)?.flatten())
+    }
+}
+
+impl<T> Graphic<T> {
+    /// Save the graphic as a REXPaint file with a single layer to some instance of `Write` (such as a file).
+    pub fn save_xp<W: Write>(&self, file: &mut W) -> io::Result<()> {
+        XpImage::from_graphic(self).save(file)
+    }
+}
@@ -0,0 +1,554 @@
+//! The SDL backends: graphics rendered to a single SDL texture, to a cache of textures for each tile,
+//! or drawn from a glyph atlas of the whole tile set. Also converts SDL events for the `ui` module.
+//! Only available with the `sdl` feature (enabled by default).
+
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap};
+use std::fs::File;
+use std::io;
+use std::path::Path;
+use std::rc::Rc;
+
+use sdl2::event::Event as SdlEvent;
+use sdl2::keyboard::{Keycode, Mod};
+use sdl2::mouse::{MouseButton, MouseWheelDirection};
+use sdl2::pixels::PixelFormatEnum;
+use sdl2::rect::{Point,Rect};
+use sdl2::render::{BlendMode, Canvas, RenderTarget, Texture, TextureCreator};
+
+use super::{Backend, Color, Draw, Graphic, Region, Tile, TileSet, TileSetVersion, TILESET_SIZE};
+use super::ui;
+
+impl TileSet {
+    
+    fn draw_tile_to<P : Into<Point>>(&self, index: usize , tex: &mut Texture, point: P, fg: Color, bg: Color) {        
+        draw_tile_data(self.data[index],tex,point, fg, bg)
+    }
+}
+
+impl Graphic<()> {
+    /// A method to attach an SDL texture, converting the graphic from an unrenderable one to a renderable one.
+    /// Commonly used straight after creation, like so:
+    /// ```
+    /// let g = Graphics::load_file("path")?.textured(texture_creator);
+    /// ```
+    /// Note that the texture has not rendered yet, so typically you would want to call `update_texture` 
+    /// and provide a tileset before drawing to screen.
+    pub fn textured<'r, T>(&self,texture_creator: &'r TextureCreator<T>) -> Graphic<Texture<'r>> {
+        let mut tex = texture_creator.create_texture_streaming(PixelFormatEnum::ARGB8888, 8 * self.width, 8 * self.height).unwrap();
+        tex.set_blend_mode(BlendMode::Blend);
+        self.with_backend(tex)
+    }
+    /// A method to attach a hash table of individual tile textures, converting the graphic from an unrenderable one to a renderable one.
+    /// Commonly used straight after creation, like so:
+    /// ```
+    /// let g = Graphics::load_file("path")?.tile_cache_textured(texture_creator);
+    /// ```
+    /// Note that the texture has not rendered yet, so typically you would want to call `update_texture` 
+    /// and provide a tileset before drawing to screen.
+    /// 
+    /// The graphic gets a cache of its own, with no limit on its size. To share a cache between
+    /// graphics, or limit its size, create a `TileCache` and attach clones of it with `with_backend`.
+    pub fn tile_cache_textured<'r, T>(&self,texture_creator: &'r TextureCreator<T>) -> Graphic<TileCache<'r,T>> {
+        self.with_backend(TileCache::new(texture_creator))
+    }
+    /// A method to attach a glyph atlas of the tile set, converting the graphic from an unrenderable one to a renderable one.
+    /// Commonly used straight after creation, like so:
+    /// ```
+    /// let g = Graphics::load_file("path")?.atlas_textured(texture_creator);
+    /// ```
+    /// Note that the atlas has not rendered yet, so typically you would want to call `update_texture` 
+    /// and provide a tileset before drawing to screen.
+    /// 
+    /// The graphic gets an atlas of its own. To share an atlas between graphics, create a `GlyphAtlas`
+    /// and attach clones of it with `with_backend`.
+    pub fn atlas_textured<'r, T>(&self,texture_creator: &'r TextureCreator<T>) -> Graphic<GlyphAtlas<'r>> {
+        self.with_backend(GlyphAtlas::new(texture_creator))
+    }
+}
+
+impl <'r>Graphic<Texture<'r>> {
+
+    /// A shortcut to load a file and associate a texture in one step. Equivalent to using `load_file` and then `textured`.
+    pub fn load_file_textured<P: AsRef<Path>,T>(path : P,texture_creator: &'r TextureCreator<T>) -> io::Result<Graphic<Texture<'r>>> {
+        let g = Graphic::load_from(File::open(path)?)?;
+        Ok(g.textured(texture_creator))
+    }
+    /// Get the SDL texture associated with this graphic.
+    pub fn texture(&self) -> &Texture {
+        &self.texture
+    }
+}
+
+/// Mostly used internally. 
+/// Given a 64 bit integer, interprets it as an 8x8 tile and draws it to the 
+/// given texture at the given point with the given foreground and background colors.
+pub fn draw_tile_data<P : Into<Point>>(data: u64 , tex: &mut Texture, point: P, fg: Color, bg: Color) {
+        let point = point.into();
+        let pixel_data = tile_pixels(data, fg, bg);
+        tex.update(Rect::new(point.x,point.y,8,8),&pixel_data, 8 * 4).unwrap();
+}
+
+/// The pixels of a tile in ARGB8888 format, row by row.
+fn tile_pixels(data: u64, fg: Color, bg: Color) -> [u8; 64 * 4] {
+        let mut m = data;
+        let mut pixel_data = [0 as u8; (64 * 4)];        
+        let mut curr = 0;
+        for _ in 0..64 {
+            let (r,g,b,a) = if (m & 0x01) != 0 { fg.rgba() } else { bg.rgba() };
+            m >>= 1;
+            pixel_data[curr] = b; curr += 1;
+            pixel_data[curr] = g; curr += 1;
+            pixel_data[curr] = r; curr += 1;
+            pixel_data[curr] = a; curr += 1;
+        }
+        pixel_data
+}
+
+impl <'r> Backend for Texture<'r> {
+    fn update_tile(&mut self, x: u32, y: u32, tile: Tile, tile_set: &TileSet) -> bool {
+        tile_set.draw_tile_to(tile.index, self, ((x * 8) as i32, (y * 8) as i32), tile.fg, tile.bg);
+        true
+    }
+    fn update_region(&mut self, region: Region, tiles: &[(Tile, bool)], tile_set: &TileSet) -> u32 {
+        let changed = tiles.iter().filter(|&&(_, c)| c).count() as u32;
+        let rect = Rect::new(region.x as i32 * 8, region.y as i32 * 8, region.width * 8, region.height * 8);
+        // Locked pixels start out undefined, so every tile in the region is rendered, not just the changed ones.
+        let locked = self.with_lock(rect, |buffer, pitch| {
+            for (i, &(t, _)) in tiles.iter().enumerate() {
+                let (x, y) = (i % region.width as usize, i / region.width as usize);
+                let pixels = tile_pixels(tile_set.data[t.index], t.fg, t.bg);
+                for row in 0..8 {
+                    let start = (y * 8 + row) * pitch + x * 8 * 4;
+                    buffer[start..start + 8 * 4].copy_from_slice(&pixels[row * 8 * 4..(row + 1) * 8 * 4]);
+                }
+            }
+        });
+        // Only streaming textures can be locked, so others are updated a tile at a time.
+        if locked.is_err() {
+            for (i, &(t, c)) in tiles.iter().enumerate() {
+                if c {
+                    self.update_tile(region.x + i as u32 % region.width, region.y + i as u32 / region.width, t, tile_set);
+                }
+            }
+        }
+        changed
+    }
+}
+
+impl <'r, T: RenderTarget> Draw<Canvas<T>> for Texture<'r> {
+    fn draw(graphic: &Graphic<Self>, canvas: &mut Canvas<T>, position: (i32, i32)) {
+        canvas.copy(&graphic.texture, None, Rect::new(position.0, position.1, graphic.width * 8, graphic.height * 8)).unwrap();
+    }
+}
+
+
+/// Counts of how a `TileCache` has been used, since it was created or the counts were reset.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct TileCacheStats {
+    /// Tiles that were already in the cache when a graphic needed them.
+    pub hits: u64,
+    /// Tiles that had to be rendered to a new texture.
+    pub misses: u64,
+    /// Textures removed from the cache to keep it within its capacity.
+    pub evictions: u64,
+}
+
+struct TileCacheEntry<'r> {
+    texture: Texture<'r>,
+    /// The version of the tile the texture was rendered from.
+    version: TileSetVersion,
+    /// How many cells of graphics using the cache are showing this tile.
+    users: usize,
+    /// When the tile was last shown, if it no longer is.
+    last_used: u64,
+}
+
+struct SharedTileCache<'r, C> {
+    texture_creator: &'r TextureCreator<C>,
+    entries: HashMap<Tile, TileCacheEntry<'r>>,
+    /// The tiles no graphic is showing, by when they were last shown, oldest first.
+    unused: BTreeMap<u64, Tile>,
+    clock: u64,
+    capacity: Option<usize>,
+    /// Incremented each time the cache is cleared for a change of tile set.
+    generation: u64,
+    stats: TileCacheStats,
+}
+
+impl <'r, C> SharedTileCache<'r, C> {
+    fn acquire(&mut self, tile: Tile) {
+        if let Some(e) = self.entries.get_mut(&tile) {
+            if e.users == 0 {
+                self.unused.remove(&e.last_used);
+            }
+            e.users += 1;
+        }
+    }
+    fn release(&mut self, tile: Tile) {
+        if let Some(e) = self.entries.get_mut(&tile) {
+            e.users -= 1;
+            if e.users == 0 {
+                self.clock += 1;
+                e.last_used = self.clock;
+                self.unused.insert(self.clock, tile);
+            }
+        }
+    }
+    /// Remove the least recently shown tiles that no graphic is showing until the cache is within its capacity.
+    fn evict(&mut self) {
+        let capacity = match self.capacity {
+            Some(c) => c,
+            None => return,
+        };
+        while self.entries.len() > capacity {
+            let oldest = match self.unused.keys().next() {
+                Some(&t) => t,
+                None => break,
+            };
+            let tile = self.unused.remove(&oldest).unwrap();
+            self.entries.remove(&tile);
+            self.stats.evictions += 1;
+        }
+    }
+}
+
+/// An alternative backend that stores each individual tile as a separate SDL texture in a hashtable.
+/// Can be used in some cases as a drop-in replacement for a textured graphic.
+/// Draw is slightly slower but updating textures is significantly faster if the same 
+/// tile is used in a lot of different places.
+///
+/// A `TileCache` is a handle to a cache that can be shared by several graphics: attach a clone of it
+/// to each graphic with `with_backend`. A cache can be given a capacity, the number of textures it
+/// keeps; tiles that no graphic is showing any more are then evicted, least recently shown first.
+/// Tiles that are being shown are never evicted, so the cache can exceed its capacity if graphics show
+/// more distinct tiles than that.
+///
+/// The cache is keyed by tile, so all the graphics sharing a cache should use the same tile set.
+/// Changes to the tile set are noticed by `update_texture`, which re-renders the changed tiles' textures.
+/// Calling `mark_dirty` on one of the graphics clears the cache for all of them, and each of them
+/// re-renders all its tiles on its next `update_texture`.
+pub struct TileCache<'r,C> {
+    shared: Rc<RefCell<SharedTileCache<'r, C>>>,
+    /// The tile this handle's graphic shows in each cell, as counted in the cache's entries.
+    cells: HashMap<(u32, u32), Tile>,
+    generation: u64,
+}
+
+impl <'r, C> TileCache<'r, C> {
+    /// A new, empty cache with no limit on its size.
+    pub fn new(texture_creator: &'r TextureCreator<C>) -> TileCache<'r, C> {
+        TileCache::with_capacity_limit(texture_creator, None)
+    }
+    /// A new, empty cache that keeps at most `capacity` textures in all, evicting tiles that aren't being shown
+    /// to stay within it.
+    pub fn with_capacity(texture_creator: &'r TextureCreator<C>, capacity: usize) -> TileCache<'r, C> {
+        TileCache::with_capacity_limit(texture_creator, Some(capacity))
+    }
+    fn with_capacity_limit(texture_creator: &'r TextureCreator<C>, capacity: Option<usize>) -> TileCache<'r, C> {
+        let shared = SharedTileCache {
+            texture_creator,
+            entries: HashMap::new(),
+            unused: BTreeMap::new(),
+            clock: 0,
+            capacity,
+            generation: 0,
+            stats: Default::default(),
+        };
+        TileCache { shared: Rc::new(RefCell::new(shared)), cells: HashMap::new(), generation: 0 }
+    }
+    /// The number of tile textures in the cache.
+    pub fn len(&self) -> usize {
+        self.shared.borrow().entries.len()
+    }
+    /// Whether the cache has no textures.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    /// The most textures the cache keeps in all, if it is limited. It can still hold more if graphics are showing more tiles.
+    pub fn capacity(&self) -> Option<usize> {
+        self.shared.borrow().capacity
+    }
+    /// Change the capacity of the cache, evicting tiles straight away if it is now over it.
+    pub fn set_capacity(&self, capacity: Option<usize>) {
+        let mut shared = self.shared.borrow_mut();
+        shared.capacity = capacity;
+        shared.evict();
+    }
+    /// How the cache has been used, by all the graphics sharing it.
+    pub fn stats(&self) -> TileCacheStats {
+        self.shared.borrow().stats
+    }
+    /// Set all the counts in `stats` back to zero.
+    pub fn reset_stats(&self) {
+        self.shared.borrow_mut().stats = Default::default();
+    }
+    /// Whether the other handle is to the same cache.
+    pub fn shares_with(&self, other: &TileCache<'r, C>) -> bool {
+        Rc::ptr_eq(&self.shared, &other.shared)
+    }
+}
+
+impl <'r, C> Clone for TileCache<'r, C> {
+    /// Another handle to the same cache. A cloned graphic's handle also counts the tiles it is showing.
+    fn clone(&self) -> TileCache<'r, C> {
+        let mut shared = self.shared.borrow_mut();
+        let cells = if self.generation == shared.generation { self.cells.clone() } else { HashMap::new() };
+        for &tile in cells.values() {
+            shared.acquire(tile);
+        }
+        // A handle whose cache has been cleared since it last rendered stays that way, so its copy renders everything again too.
+        TileCache { shared: self.shared.clone(), cells, generation: self.generation }
+    }
+}
+
+impl <'r, C> Drop for TileCache<'r, C> {
+    fn drop(&mut self) {
+        let mut shared = self.shared.borrow_mut();
+        if self.generation == shared.generation {
+            for &tile in self.cells.values() {
+                shared.release(tile);
+            }
+            shared.evict();
+        }
+    }
+}
+
+impl <'r, C> Backend for TileCache<'r, C> {
+    fn update_tile(&mut self, x: u32, y: u32, tile: Tile, tile_set: &TileSet) -> bool {
+        let mut shared = self.shared.borrow_mut();
+        if self.generation != shared.generation {
+            // The cache has been cleared since this graphic last rendered, so none of its cells are counted.
+            self.cells.clear();
+            self.generation = shared.generation;
+        }
+        let version = tile_set.tile_version(tile.index);
+        let rendered = match shared.entries.get_mut(&tile) {
+            Some(e) if e.version == version => {
+                shared.stats.hits += 1;
+                false
+            }
+            // The tile has changed since it was rendered, so its texture is rendered again in place.
+            Some(e) => {
+                tile_set.draw_tile_to(tile.index, &mut e.texture, (0,0), tile.fg, tile.bg);
+                e.version = version;
+                shared.stats.misses += 1;
+                true
+            }
+            None => {
+                let mut texture = shared.texture_creator.create_texture_streaming(PixelFormatEnum::ARGB8888, 8, 8).unwrap();
+                texture.set_blend_mode(BlendMode::Blend);
+                tile_set.draw_tile_to(tile.index, &mut texture, (0,0), tile.fg, tile.bg);
+                shared.entries.insert(tile, TileCacheEntry { texture, version, users: 0, last_used: 0 });
+                shared.stats.misses += 1;
+                true
+            }
+        };
+        shared.acquire(tile);
+        if let Some(old) = self.cells.insert((x, y), tile) {
+            shared.release(old);
+        }
+        shared.evict();
+        rendered
+    }
+    fn invalidate(&mut self) {
+        let mut shared = self.shared.borrow_mut();
+        // Only the first of the graphics sharing the cache to be marked dirty since it was last cleared clears it.
+        if self.generation == shared.generation {
+            shared.entries.clear();
+            shared.unused.clear();
+            shared.generation += 1;
+        }
+        self.cells.clear();
+        self.generation = shared.generation;
+    }
+    fn needs_full_update(&self) -> bool {
+        self.generation != self.shared.borrow().generation
+    }
+}
+
+impl <'r, C, T: RenderTarget> Draw<Canvas<T>> for TileCache<'r, C> {
+    fn draw(graphic: &Graphic<Self>, canvas: &mut Canvas<T>, position: (i32, i32)) {
+        let shared = graphic.texture.shared.borrow();
+        let mut i = 0;
+        for y in 0..graphic.height {
+            for x in 0..graphic.width {
+                let t = graphic.tiles[i];
+                if let Some(e) = shared.entries.get(&t) {
+                    canvas.copy(&e.texture, None, Rect::new(position.0 + x as i32 * 8, position.1 + y as i32 * 8, 8, 8)).unwrap();
+                }
+                i += 1
+            }
+        }
+    }
+}
+
+/// The number of tiles in each row of a `GlyphAtlas`'s texture.
+const ATLAS_COLUMNS: u32 = 32;
+
+struct SharedGlyphAtlas<'r> {
+    texture: Texture<'r>,
+    /// The version of the tile set the texture was rendered from, if it has been.
+    version: Option<TileSetVersion>,
+}
+
+/// A backend that renders the whole tile set once to a texture, as white glyphs on a transparent background,
+/// and draws each tile as a rectangle of its background color with its glyph copied over it, tinted with its
+/// foreground color.
+///
+/// Changing tiles costs nothing until they are drawn, so this is the fastest backend for graphics that change
+/// a lot, such as when recoloring a whole screen every frame, at the cost of two draw calls per tile when drawing.
+///
+/// Like `TileCache`, a `GlyphAtlas` is a handle that can be shared by several graphics using the same tile set,
+/// by attaching a clone of it to each graphic with `with_backend`. Changes to the tile set are noticed by
+/// `update_texture`, which renders only the changed glyphs again.
+pub struct GlyphAtlas<'r> {
+    shared: Rc<RefCell<SharedGlyphAtlas<'r>>>,
+}
+
+impl <'r> GlyphAtlas<'r> {
+    /// A new atlas, rendered from the tile set given to the first `update_texture` of a graphic using it.
+    pub fn new<C>(texture_creator: &'r TextureCreator<C>) -> GlyphAtlas<'r> {
+        let rows = (TILESET_SIZE as u32).div_ceil(ATLAS_COLUMNS);
+        let mut texture = texture_creator.create_texture_streaming(PixelFormatEnum::ARGB8888, ATLAS_COLUMNS * 8, rows * 8).unwrap();
+        texture.set_blend_mode(BlendMode::Blend);
+        GlyphAtlas { shared: Rc::new(RefCell::new(SharedGlyphAtlas { texture, version: None })) }
+    }
+    /// Whether the other handle is to the same atlas.
+    pub fn shares_with(&self, other: &GlyphAtlas<'r>) -> bool {
+        Rc::ptr_eq(&self.shared, &other.shared)
+    }
+}
+
+impl <'r> Clone for GlyphAtlas<'r> {
+    /// Another handle to the same atlas.
+    fn clone(&self) -> GlyphAtlas<'r> {
+        GlyphAtlas { shared: self.shared.clone() }
+    }
+}
+
+/// Where the tile with the given index is in an atlas's texture.
+fn atlas_rect(index: usize) -> Rect {
+    let (column, row) = (index as u32 % ATLAS_COLUMNS, index as u32 / ATLAS_COLUMNS);
+    Rect::new(column as i32 * 8, row as i32 * 8, 8, 8)
+}
+
+impl <'r> Backend for GlyphAtlas<'r> {
+    fn update_tile(&mut self, _x: u32, _y: u32, _tile: Tile, tile_set: &TileSet) -> bool {
+        let mut shared = self.shared.borrow_mut();
+        let white = Color::RGBA(255, 255, 255, 255);
+        let transparent = Color::RGBA(255, 255, 255, 0);
+        match shared.version.and_then(|v| tile_set.tiles_changed_since(v)) {
+            Some(changed) => {
+                for &index in changed.iter().filter(|&&i| i < TILESET_SIZE) {
+                    let glyph = tile_pixels(tile_set[index], white, transparent);
+                    shared.texture.update(atlas_rect(index), &glyph, 8 * 4).unwrap();
+                }
+            }
+            None => {
+                let (width, height) = (shared.texture.query().width, shared.texture.query().height);
+                let pitch = width as usize * 4;
+                let mut pixels = vec![0; pitch * height as usize];
+                for index in 0..tile_set.len().min(TILESET_SIZE) {
+                    let glyph = tile_pixels(tile_set[index], white, transparent);
+                    let rect = atlas_rect(index);
+                    for row in 0..8 {
+                        let start = (rect.y() as usize + row) * pitch + rect.x() as usize * 4;
+                        pixels[start..start + 8 * 4].copy_from_slice(&glyph[row * 8 * 4..(row + 1) * 8 * 4]);
+                    }
+                }
+                shared.texture.update(None, &pixels, pitch).unwrap();
+            }
+        }
+        let rendered = shared.version != Some(tile_set.version());
+        shared.version = Some(tile_set.version());
+        rendered
+    }
+    fn update_region(&mut self, region: Region, tiles: &[(Tile, bool)], tile_set: &TileSet) -> u32 {
+        // The tiles themselves need no rendering, only the atlas if the tile set has changed.
+        if let Some(&(tile, _)) = tiles.first() {
+            self.update_tile(region.x, region.y, tile, tile_set);
+        }
+        tiles.iter().filter(|&&(_, changed)| changed).count() as u32
+    }
+    fn invalidate(&mut self) {
+        self.shared.borrow_mut().version = None;
+    }
+}
+
+impl <'r, T: RenderTarget> Draw<Canvas<T>> for GlyphAtlas<'r> {
+    fn draw(graphic: &Graphic<Self>, canvas: &mut Canvas<T>, position: (i32, i32)) {
+        let mut shared = graphic.texture.shared.borrow_mut();
+        let (draw_color, blend_mode) = (canvas.draw_color(), canvas.blend_mode());
+        canvas.set_blend_mode(BlendMode::Blend);
+        let mut tint = None;
+        let mut i = 0;
+        for y in 0..graphic.height {
+            for x in 0..graphic.width {
+                let t = graphic.tiles[i];
+                let dest = Rect::new(position.0 + x as i32 * 8, position.1 + y as i32 * 8, 8, 8);
+                if t.bg.a != 0 {
+                    canvas.set_draw_color(t.bg);
+                    canvas.fill_rect(dest).unwrap();
+                }
+                if t.fg.a != 0 && t.index < TILESET_SIZE {
+                    if tint != Some(t.fg) {
+                        shared.texture.set_color_mod(t.fg.r, t.fg.g, t.fg.b);
+                        shared.texture.set_alpha_mod(t.fg.a);
+                        tint = Some(t.fg);
+                    }
+                    canvas.copy(&shared.texture, atlas_rect(t.index), dest).unwrap();
+                }
+                i += 1
+            }
+        }
+        canvas.set_draw_color(draw_color);
+        canvas.set_blend_mode(blend_mode);
+    }
+}
+
+impl ui::Event {
+    /// Convert an SDL event into a UI event, for a UI drawn with its top-left corner at `origin` (in pixels)
+    /// with tiles `tile_size` pixels across (8, unless the canvas is scaled). Returns `None` for events that
+    /// widgets have no use for. Text events only arrive once text input is started, with `VideoSubsystem::text_input`.
+    pub fn from_sdl(event: &SdlEvent, origin: (i32, i32), tile_size: i32) -> Option<ui::Event> {
+        let tile = |x: i32, y: i32| ((x - origin.0).div_euclid(tile_size), (y - origin.1).div_euclid(tile_size));
+        Some(match *event {
+            SdlEvent::KeyDown { keycode: Some(keycode), keymod, .. } => ui::Event::Key(match keycode {
+                Keycode::Up => ui::Key::Up,
+                Keycode::Down => ui::Key::Down,
+                Keycode::Left => ui::Key::Left,
+                Keycode::Right => ui::Key::Right,
+                Keycode::Home => ui::Key::Home,
+                Keycode::End => ui::Key::End,
+                Keycode::PageUp => ui::Key::PageUp,
+                Keycode::PageDown => ui::Key::PageDown,
+                Keycode::Return | Keycode::KpEnter => ui::Key::Enter,
+                Keycode::Escape => ui::Key::Escape,
+                Keycode::Space => ui::Key::Space,
+                Keycode::Tab if keymod.intersects(Mod::LSHIFTMOD | Mod::RSHIFTMOD) => ui::Key::BackTab,
+                Keycode::Tab => ui::Key::Tab,
+                Keycode::Backspace => ui::Key::Backspace,
+                Keycode::Delete => ui::Key::Delete,
+                _ => return None,
+            }),
+            SdlEvent::TextInput { ref text, .. } => ui::Event::Text(text.clone()),
+            SdlEvent::MouseButtonDown { mouse_btn: MouseButton::Left, x, y, .. } => {
+                let (x, y) = tile(x, y);
+                ui::Event::MouseDown { x, y }
+            }
+            SdlEvent::MouseButtonUp { mouse_btn: MouseButton::Left, x, y, .. } => {
+                let (x, y) = tile(x, y);
+                ui::Event::MouseUp { x, y }
+            }
+            SdlEvent::MouseMotion { x, y, .. } => {
+                let (x, y) = tile(x, y);
+                ui::Event::MouseMove { x, y }
+            }
+            // SDL counts turning the wheel away from the user as positive, which scrolls up.
+            SdlEvent::MouseWheel { y, direction: MouseWheelDirection::Flipped, .. } if y != 0 => ui::Event::Wheel(y),
+            SdlEvent::MouseWheel { y, .. } if y != 0 => ui::Event::Wheel(-y),
+            _ => return None,
+        })
+    }
+}
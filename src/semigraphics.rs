@@ -0,0 +1,244 @@
+//! Sub-tile pixel plotting using the block graphics characters found in
+//! many tile sets (such as the quadrant blocks in PETscii and the half blocks in CP437).
+//!
+//! Each cell of a `Graphic` is treated as a tiny 2x2 (quadrant) or 2x3 (sextant)
+//! grid of pixels. Plotting a pixel picks whichever block glyph in the tile set
+//! best matches the resulting pattern, and merges with whatever block glyph is
+//! already in that cell. As each tile has only two colors, plotting a pixel
+//! in a new color recolors all other lit pixels in the same cell.
+//!
+//! ```ignore
+//! let blocks = BlockGlyphs::find(&TileSet::petscii(), BlockResolution::Quadrants).unwrap();
+//! graphic.plot(&blocks, 3, 5, Color::RGB(255,0,0));
+//! ```
+
+use super::{Color, Graphic, Tile, TileSet};
+
+/// How many pixels each cell is divided into.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum BlockResolution {
+    /// 2x2 pixels per cell, each 4x4 pixels of the tile.
+    Quadrants,
+    /// 2x3 pixels per cell. As 8 rows do not divide evenly into three,
+    /// the rows are split 3, 2 and 3 pixels high.
+    Sextants,
+}
+
+impl BlockResolution {
+    /// The number of pixels across and down in each cell.
+    pub fn cell_size(self) -> (u32, u32) {
+        match self {
+            BlockResolution::Quadrants => (2, 2),
+            BlockResolution::Sextants => (2, 3),
+        }
+    }
+    /// The number of distinct on/off patterns a cell can show.
+    pub fn patterns(self) -> usize {
+        let (w, h) = self.cell_size();
+        1 << (w * h)
+    }
+    /// Which pixel row of the cell each of the tile's 8 rows belongs to.
+    fn row_bands(self) -> [u32; 8] {
+        match self {
+            BlockResolution::Quadrants => [0, 0, 0, 0, 1, 1, 1, 1],
+            BlockResolution::Sextants => [0, 0, 0, 1, 1, 2, 2, 2],
+        }
+    }
+    /// The ideal tile data for a pattern, where bit `x + y * 2` of the pattern is the pixel at `(x,y)`.
+    pub fn tile_data(self, pattern: u8) -> u64 {
+        let bands = self.row_bands();
+        let mut data = 0;
+        for (row, band) in bands.iter().enumerate() {
+            if pattern & (1 << (band * 2)) != 0 {
+                data |= 0x0F << (row * 8);
+            }
+            if pattern & (1 << (band * 2 + 1)) != 0 {
+                data |= 0xF0 << (row * 8);
+            }
+        }
+        data
+    }
+    /// Recover the pattern shown by the given tile data, if each half of every row
+    /// is either fully lit or fully unlit. Pixels are lit if at least half of
+    /// the rows they cover are lit.
+    fn pattern_of(self, data: u64) -> Option<u8> {
+        let bands = self.row_bands();
+        let mut lit = [0u32; 6];
+        let mut rows = [0u32; 3];
+        for (row, band) in bands.iter().enumerate() {
+            let b = (data >> (row * 8)) & 0xFF;
+            let (left, right) = (b & 0x0F, b & 0xF0);
+            if (left != 0 && left != 0x0F) || (right != 0 && right != 0xF0) {
+                return None;
+            }
+            let band = *band as usize;
+            rows[band] += 1;
+            if left != 0 { lit[band * 2] += 1 }
+            if right != 0 { lit[band * 2 + 1] += 1 }
+        }
+        let (w, h) = self.cell_size();
+        let mut pattern = 0;
+        for i in 0..(w * h) as usize {
+            if lit[i] * 2 >= rows[i / 2] {
+                pattern |= 1 << i;
+            }
+        }
+        Some(pattern)
+    }
+}
+
+/// The 2x2 quadrant pattern closest to the given tile data, where each quadrant
+/// is lit if at least half of its pixels are. Bits are numbered as in `BlockResolution::tile_data`.
+pub(crate) fn nearest_quadrants(data: u64) -> u8 {
+    let mut pattern = 0;
+    for q in 0..4 {
+        let mask = 0x0F0F_0F0F << ((q % 2) * 4 + (q / 2) * 32);
+        if (data & mask).count_ones() >= 8 {
+            pattern |= 1 << q;
+        }
+    }
+    pattern
+}
+
+/// A table of the block glyphs available in a particular tile set, used to plot
+/// pixels into a `Graphic`.
+///
+/// A glyph showing a pattern drawn with its colors swapped shows the inverse pattern,
+/// so tile sets need only contain half of the patterns to be usable.
+/// If a pattern (or its inverse) does not exist in the tile set at all,
+/// the closest available glyph is used instead.
+#[derive(Clone, Debug)]
+pub struct BlockGlyphs {
+    resolution: BlockResolution,
+    glyphs: Vec<(usize, bool)>,
+    patterns: Vec<Option<u8>>,
+}
+
+impl BlockGlyphs {
+    /// Search the given tile set for block glyphs at the given resolution.
+    /// Returns `None` if the tile set has no glyphs made of blocks at all,
+    /// not even a blank or solid tile.
+    pub fn find(tile_set: &TileSet, resolution: BlockResolution) -> Option<BlockGlyphs> {
+        let mut patterns = Vec::with_capacity(tile_set.len());
+        let mut candidates = Vec::new();
+        for i in 0..tile_set.len() {
+            let p = resolution.pattern_of(tile_set[i]);
+            if p.is_some() {
+                candidates.push(i);
+            }
+            patterns.push(p);
+        }
+        if candidates.is_empty() {
+            return None;
+        }
+        let mut glyphs = Vec::with_capacity(resolution.patterns());
+        for p in 0..resolution.patterns() {
+            let ideal = resolution.tile_data(p as u8);
+            let mut best = (candidates[0], false);
+            let mut best_score = (u32::MAX, u32::MAX, u32::MAX);
+            for &i in &candidates {
+                for &inverted in &[false, true] {
+                    let data = if inverted { !tile_set[i] } else { tile_set[i] };
+                    // Ties are broken in favour of glyphs that show every lit pixel,
+                    // so that a lone pixel is never drawn as a blank cell, and then of
+                    // glyphs that needn't be inverted, so that cells can be read back.
+                    let score = ((data ^ ideal).count_ones(), (ideal & !data).count_ones(), inverted as u32);
+                    if score < best_score {
+                        best = (i, inverted);
+                        best_score = score;
+                    }
+                }
+            }
+            glyphs.push(best);
+        }
+        Some(BlockGlyphs { resolution, glyphs, patterns })
+    }
+    /// The resolution of the blocks in this table.
+    pub fn resolution(&self) -> BlockResolution {
+        self.resolution
+    }
+    /// The tile index used to show the given pattern, and whether the
+    /// foreground and background colors must be swapped to show it.
+    pub fn glyph(&self, pattern: u8) -> (usize, bool) {
+        self.glyphs[pattern as usize % self.glyphs.len()]
+    }
+    /// The pattern shown by the given tile index in its foreground color,
+    /// or `None` if that tile is not a block glyph.
+    pub fn pattern(&self, index: usize) -> Option<u8> {
+        self.patterns.get(index).and_then(|p| *p)
+    }
+    /// Create a tile showing the given pattern with the given colors.
+    pub fn tile(&self, pattern: u8, fg: Color, bg: Color) -> Tile {
+        match self.glyph(pattern) {
+            (index, false) => Tile { index, fg, bg },
+            (index, true) => Tile { index, fg: bg, bg: fg },
+        }
+    }
+    /// The pattern a tile shows, with the colors of its lit and unlit pixels. A tile made by `tile` with
+    /// an inverted glyph shows the inverse of the glyph's pattern, with its colors swapped back.
+    /// If a tile set has a glyph for only one of a pattern and its inverse, a tile with the glyph inverted
+    /// can't be told apart from one with the glyph as it is, and is taken to be the latter.
+    fn cell(&self, t: Tile) -> (u8, Color, Color) {
+        let pattern = self.pattern(t.index).unwrap_or(0);
+        let inverse = !pattern & (self.resolution.patterns() - 1) as u8;
+        if self.glyph(pattern) != (t.index, false) && self.glyph(inverse) == (t.index, true) {
+            (inverse, t.bg, t.fg)
+        } else {
+            (pattern, t.fg, t.bg)
+        }
+    }
+    fn locate(&self, x: u32, y: u32) -> (u32, u32, u8) {
+        let (w, h) = self.resolution.cell_size();
+        (x / w, y / h, 1 << ((x % w) + (y % h) * w))
+    }
+}
+
+impl <T> Graphic<T> {
+    /// The width of the graphic in block pixels.
+    pub fn block_width(&self, blocks: &BlockGlyphs) -> u32 {
+        self.width * blocks.resolution.cell_size().0
+    }
+    /// The height of the graphic in block pixels.
+    pub fn block_height(&self, blocks: &BlockGlyphs) -> u32 {
+        self.height * blocks.resolution.cell_size().1
+    }
+    /// Set the block pixel at `(x,y)` to the given color, merging it with the
+    /// other pixels already in that cell. If the color matches the background
+    /// color of the cell, the pixel is cleared instead. Otherwise, any other lit
+    /// pixels in the cell take on the new color too.
+    ///
+    /// Cells that do not contain a block glyph are treated as empty.
+    /// Does nothing if the pixel is out of bounds.
+    pub fn plot(&mut self, blocks: &BlockGlyphs, x: u32, y: u32, color: Color) {
+        let (cx, cy, bit) = blocks.locate(x, y);
+        if cx < self.width && cy < self.height {
+            let (pattern, fg, bg) = blocks.cell(self.get_tile(cx, cy));
+            let tile = if color == bg {
+                blocks.tile(pattern & !bit, fg, bg)
+            } else {
+                blocks.tile(pattern | bit, color, bg)
+            };
+            self.set_tile(cx, cy, tile);
+        }
+    }
+    /// Clear the block pixel at `(x,y)` so that it shows the background color of its cell.
+    /// Does nothing if the pixel is out of bounds.
+    pub fn unplot(&mut self, blocks: &BlockGlyphs, x: u32, y: u32) {
+        let (cx, cy, bit) = blocks.locate(x, y);
+        if cx < self.width && cy < self.height {
+            let (pattern, fg, bg) = blocks.cell(self.get_tile(cx, cy));
+            self.set_tile(cx, cy, blocks.tile(pattern & !bit, fg, bg));
+        }
+    }
+    /// The color of the block pixel at `(x,y)`, or `None` if it is out of bounds
+    /// or its cell does not contain a block glyph.
+    pub fn pixel(&self, blocks: &BlockGlyphs, x: u32, y: u32) -> Option<Color> {
+        let (cx, cy, bit) = blocks.locate(x, y);
+        if cx < self.width && cy < self.height {
+            let t = self.get_tile(cx, cy);
+            blocks.pattern(t.index).map(|p| if p & bit != 0 { t.fg } else { t.bg })
+        } else {
+            None
+        }
+    }
+}
@@ -0,0 +1,168 @@
+//! `Serialize` and `Deserialize` implementations for use with serde, behind the `serde` feature.
+//!
+//! The representations are meant to be compact and readable in formats like RON and JSON:
+//!
+//! * A `Color` is a string `"#rrggbb"`, or `"#rrggbbaa"` if it isn't opaque.
+//! * A `Tile` is a tuple of its index and foreground and background colors.
+//! * A `Graphic` is a struct of its `width`, `height` and `tiles` (row by row). Any graphic can be
+//!   serialized, but only a `Graphic<()>` can be deserialized; attach a backend afterwards.
+//! * A `TileSet` is a struct of its `tiles`, as 16 digit hex strings of the tile data with
+//!   trailing blank tiles left out, and its `char_map`, mapping characters to tile indices with
+//!   characters mapped to tile 0 left out. Any `TileProperties` are in its `properties`, by tile index.
+//! * A `Swatch` is a sequence of colors, with the trailing transparent colors left out.
+
+use std::collections::BTreeMap;
+
+use serde::de::{self, Deserialize, Deserializer};
+use serde::ser::{Serialize, SerializeTuple, Serializer};
+use serde::{Deserialize as DeriveDeserialize, Serialize as DeriveSerialize};
+
+use super::{Color, Graphic, Swatch, Tile, TileProperties, TileSet};
+
+impl Serialize for Color {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Color {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Color, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        Color::from_hex(&s).ok_or_else(|| de::Error::invalid_value(de::Unexpected::Str(&s), &"a color such as \"#rrggbb\" or \"#rrggbbaa\""))
+    }
+}
+
+impl Serialize for Tile {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut t = serializer.serialize_tuple(3)?;
+        t.serialize_element(&self.index)?;
+        t.serialize_element(&self.fg)?;
+        t.serialize_element(&self.bg)?;
+        t.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for Tile {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Tile, D::Error> {
+        let (index, fg, bg) = <(usize, Color, Color)>::deserialize(deserializer)?;
+        Ok(Tile { index, fg, bg })
+    }
+}
+
+#[derive(DeriveSerialize)]
+#[serde(rename = "Graphic")]
+struct GraphicRef<'a> {
+    width: u32,
+    height: u32,
+    tiles: &'a [Tile],
+}
+
+#[derive(DeriveDeserialize)]
+#[serde(rename = "Graphic")]
+struct GraphicData {
+    width: u32,
+    height: u32,
+    tiles: Vec<Tile>,
+}
+
+impl<T> Serialize for Graphic<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        GraphicRef { width: self.width, height: self.height, tiles: &self.tiles }.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Graphic<()> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Graphic<()>, D::Error> {
+        let data = GraphicData::deserialize(deserializer)?;
+        let size = data.width as usize * data.height as usize;
+        if data.tiles.len() != size {
+            return Err(de::Error::invalid_length(data.tiles.len(), &format!("{} tiles for a {}x{} graphic", size, data.width, data.height).as_str()));
+        }
+        let mut g = Graphic::blank(data.width, data.height);
+        g.tiles = data.tiles;
+        Ok(g)
+    }
+}
+
+/// Tile data as a hex string.
+struct TileData(u64);
+
+impl Serialize for TileData {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(&format_args!("{:016x}", self.0))
+    }
+}
+
+impl<'de> Deserialize<'de> for TileData {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<TileData, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        let digits = !s.is_empty() && s.len() <= 16 && s.bytes().all(|b| b.is_ascii_hexdigit());
+        let data = if digits { u64::from_str_radix(&s, 16).ok() } else { None };
+        data.map(TileData).ok_or_else(|| de::Error::invalid_value(de::Unexpected::Str(&s), &"tile data as up to 16 hex digits"))
+    }
+}
+
+#[derive(DeriveSerialize, DeriveDeserialize)]
+#[serde(rename = "TileSet")]
+struct TileSetData {
+    tiles: Vec<TileData>,
+    char_map: BTreeMap<char, usize>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    properties: BTreeMap<usize, TileProperties>,
+}
+
+impl Serialize for TileSet {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let used = self.data.iter().rposition(|&t| t != 0).map_or(0, |i| i + 1);
+        TileSetData {
+            tiles: self.data[..used].iter().map(|&t| TileData(t)).collect(),
+            char_map: self.char_map.iter().enumerate().filter(|&(_, &i)| i != 0).map(|(c, &i)| (c as u8 as char, i)).collect(),
+            properties: self.properties.iter().filter(|(_, p)| !p.is_empty()).map(|(&i, p)| (i, p.clone())).collect(),
+        }.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for TileSet {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<TileSet, D::Error> {
+        let data = TileSetData::deserialize(deserializer)?;
+        let mut ts = TileSet::blank();
+        if data.tiles.len() > ts.len() {
+            return Err(de::Error::invalid_length(data.tiles.len(), &"at most 512 tiles"));
+        }
+        for (i, t) in data.tiles.into_iter().enumerate() {
+            ts[i] = t.0;
+        }
+        ts.char_map = [0; 256];
+        for (c, i) in data.char_map {
+            if c as u32 > 0xff {
+                return Err(de::Error::invalid_value(de::Unexpected::Char(c), &"a character from U+0000 to U+00FF"));
+            }
+            ts[c] = i;
+        }
+        for (i, p) in data.properties {
+            if i >= ts.len() {
+                return Err(de::Error::invalid_value(de::Unexpected::Unsigned(i as u64), &"a tile index below 512"));
+            }
+            ts.set_tile_properties(i, p);
+        }
+        Ok(ts)
+    }
+}
+
+impl Serialize for Swatch {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let blank = Swatch::from_colors(&[]);
+        let used = (0..256).rev().find(|&i| self[i] != blank[i]).map_or(0, |i| i + 1);
+        serializer.collect_seq((0..used).map(|i| self[i]))
+    }
+}
+
+impl<'de> Deserialize<'de> for Swatch {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Swatch, D::Error> {
+        let colors = Vec::<Color>::deserialize(deserializer)?;
+        if colors.len() > 256 {
+            return Err(de::Error::invalid_length(colors.len(), &"at most 256 colors"));
+        }
+        Ok(Swatch::from_colors(&colors))
+    }
+}
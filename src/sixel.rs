@@ -0,0 +1,112 @@
+//! Rendering graphics as DEC Sixel images, which terminals such as xterm (with `-ti vt340`),
+//! mlterm and foot show inline. Useful for previewing graphics from command line tools
+//! without opening a window.
+//!
+//! ```ignore
+//! g.write_sixel(&tile_set, 2, &mut io::stdout())?;
+//! ```
+
+use std::collections::HashMap;
+use std::io;
+use std::io::Write;
+
+use super::{Color, Graphic, Swatch, TileSet};
+
+/// The number of color registers that terminals are generally assumed to have.
+const REGISTERS: usize = 256;
+
+/// Write the run of the same sixel character, using the repeat introducer for long runs.
+fn write_run<W: Write>(out: &mut W, sixel: u8, count: usize) -> io::Result<()> {
+    if count > 3 {
+        write!(out, "!{}{}", count, sixel as char)
+    } else {
+        for _ in 0..count {
+            out.write_all(&[sixel])?;
+        }
+        Ok(())
+    }
+}
+
+impl <T> Graphic<T> {
+    /// Write the whole graphic to the given output as a Sixel image, with each pixel of the tiles
+    /// drawn as a `scale` x `scale` square.
+    ///
+    /// A color register is defined for each color used by the graphic, or if there are more than
+    /// 256 colors, for the 256 used most, with the rest drawn as the nearest of those.
+    /// Fully transparent pixels are not drawn, leaving the terminal's background.
+    pub fn write_sixel<W: Write>(&self, tile_set: &TileSet, scale: u32, out: &mut W) -> io::Result<()> {
+        let scale = scale.max(1) as usize;
+        let (width, height) = (self.width as usize * 8 * scale, self.height as usize * 8 * scale);
+        let color_at = |x: usize, y: usize| {
+            let (x, y) = (x / scale, y / scale);
+            let t = self.tiles[x / 8 + (y / 8) * self.width as usize];
+            let data = if t.index < tile_set.len() { tile_set[t.index] } else { 0 };
+            let c = if data & (1 << (x % 8 + (y % 8) * 8)) != 0 { t.fg } else { t.bg };
+            if c.a == 0 { None } else { Some(Color::RGB(c.r, c.g, c.b)) }
+        };
+        let mut counts : HashMap<Color, usize> = HashMap::new();
+        for t in &self.tiles {
+            for &c in [t.fg, t.bg].iter() {
+                if c.a != 0 {
+                    *counts.entry(Color::RGB(c.r, c.g, c.b)).or_insert(0) += 1;
+                }
+            }
+        }
+        let mut colors : Vec<(Color, usize)> = counts.into_iter().collect();
+        colors.sort_by_key(|&(c, n)| (std::cmp::Reverse(n), c.r, c.g, c.b));
+        let colors : Vec<Color> = colors.into_iter().map(|(c, _)| c).collect();
+        let swatch = Swatch::from_colors(&colors);
+        let registers = colors.len().min(REGISTERS);
+        let mut register_of : HashMap<Color, usize> = HashMap::new();
+        for (i, &c) in colors.iter().enumerate() {
+            register_of.insert(c, if i < registers { i } else { swatch.nearest(c, registers) });
+        }
+        // Pixel aspect ratio 1:1, and unset pixels are left as they are.
+        write!(out, "\x1bP0;1;0q\"1;1;{};{}", width, height)?;
+        for (i, c) in colors.iter().take(registers).enumerate() {
+            let percent = |v: u8| (v as u32 * 100 + 127) / 255;
+            write!(out, "#{};2;{};{};{}", i, percent(c.r), percent(c.g), percent(c.b))?;
+        }
+        let mut band = vec![None; width * 6];
+        for top in (0..height).step_by(6) {
+            let rows = (height - top).min(6);
+            let mut used = vec![false; registers];
+            for y in 0..rows {
+                for x in 0..width {
+                    let r = color_at(x, top + y).map(|c| register_of[&c]);
+                    if let Some(r) = r { used[r] = true }
+                    band[x + y * width] = r;
+                }
+            }
+            let mut first = true;
+            for (r, _) in used.iter().enumerate().filter(|&(_, &u)| u) {
+                if !first {
+                    out.write_all(b"$")?;
+                }
+                first = false;
+                write!(out, "#{}", r)?;
+                let mut run = (0, 0);
+                for x in 0..width {
+                    let mut bits = 0;
+                    for y in 0..rows {
+                        if band[x + y * width] == Some(r) { bits |= 1 << y }
+                    }
+                    let sixel = 0x3F + bits;
+                    if sixel == run.0 {
+                        run.1 += 1;
+                    } else {
+                        write_run(out, run.0, run.1)?;
+                        run = (sixel, 1);
+                    }
+                }
+                // Trailing empty sixels needn't be written.
+                if run.0 != 0x3F {
+                    write_run(out, run.0, run.1)?;
+                }
+            }
+            out.write_all(b"-")?;
+        }
+        out.write_all(b"\x1b\\")?;
+        out.flush()
+    }
+}
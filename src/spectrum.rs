@@ -0,0 +1,150 @@
+//! Loading and saving ZX Spectrum screens (`.scr`), the 6912 byte dumps of the Spectrum's
+//! display memory.
+//!
+//! The Spectrum's screen is 32x24 cells, each an 8x8 bitmap with an attribute byte giving
+//! its ink (foreground) and paper (background) colors, so it maps directly onto a `Graphic`.
+//! As the bitmaps can be anything, loading a screen also builds a `TileSet` of the distinct
+//! bitmaps it uses.
+
+use std::fs::File;
+use std::io;
+use std::io::{Read, Write};
+use std::path::Path;
+use std::collections::HashMap;
+
+use super::{Color, Graphic, Tile, TileSet};
+
+const WIDTH: u32 = 32;
+const HEIGHT: u32 = 24;
+const BITMAP_SIZE: usize = 6144;
+const SCR_SIZE: usize = BITMAP_SIZE + (WIDTH * HEIGHT) as usize;
+
+const fn rgb(r: u8, g: u8, b: u8) -> Color {
+    Color { r, g, b, a: 255 }
+}
+
+/// The Spectrum's colors: the eight normal colors (black, blue, red, magenta, green, cyan,
+/// yellow, white) followed by their bright versions. Bright black is the same as black.
+pub const SPECTRUM_PALETTE: [Color; 16] = [
+    rgb(0x00, 0x00, 0x00), rgb(0x00, 0x00, 0xD7), rgb(0xD7, 0x00, 0x00), rgb(0xD7, 0x00, 0xD7),
+    rgb(0x00, 0xD7, 0x00), rgb(0x00, 0xD7, 0xD7), rgb(0xD7, 0xD7, 0x00), rgb(0xD7, 0xD7, 0xD7),
+    rgb(0x00, 0x00, 0x00), rgb(0x00, 0x00, 0xFF), rgb(0xFF, 0x00, 0x00), rgb(0xFF, 0x00, 0xFF),
+    rgb(0x00, 0xFF, 0x00), rgb(0x00, 0xFF, 0xFF), rgb(0xFF, 0xFF, 0x00), rgb(0xFF, 0xFF, 0xFF),
+];
+
+/// The offset in the bitmap of the byte holding the given pixel row (0 to 191) of the given column of cells.
+fn bitmap_offset(column: u32, row: u32) -> usize {
+    (((row & 0xC0) << 5) | ((row & 0x07) << 8) | ((row & 0x38) << 2) | column) as usize
+}
+
+fn distance(a: Color, b: Color) -> u32 {
+    let d = |x: u8, y: u8| (x as i32 - y as i32).pow(2) as u32;
+    d(a.r, b.r) + d(a.g, b.g) + d(a.b, b.b)
+}
+
+/// The nearest of the eight colors at the given brightness, and how far it is from the color.
+fn nearest(c: Color, bright: bool) -> (u8, u32) {
+    let base = if bright { 8 } else { 0 };
+    (0..8).map(|i| (i as u8, distance(c, SPECTRUM_PALETTE[base + i]))).min_by_key(|&(_, d)| d).unwrap()
+}
+
+/// The attribute byte that best fits a pair of colors, and whether it fits exactly.
+fn attribute(fg: Color, bg: Color) -> (u8, bool) {
+    let (ink, paper, bright, d) = [false, true].iter().map(|&bright| {
+        let (ink, di) = nearest(fg, bright);
+        let (paper, dp) = nearest(bg, bright);
+        (ink, paper, bright, di + dp)
+    }).min_by_key(|&(_, _, _, d)| d).unwrap();
+    (ink | (paper << 3) | if bright { 0x40 } else { 0 }, d == 0)
+}
+
+/// The contents of a Spectrum screen.
+#[derive(Clone)]
+pub struct Scr {
+    pub tile_set: TileSet,
+    pub graphic: Graphic<()>,
+}
+
+impl Scr {
+    /// Bundle a graphic with the tile set to save it with.
+    pub fn from_graphic<T>(graphic: &Graphic<T>, tile_set: &TileSet) -> Scr {
+        let mut g = Graphic::blank(graphic.width, graphic.height);
+        g.copy_all_tiles_from(graphic, 0, 0);
+        Scr { tile_set: tile_set.clone(), graphic: g }
+    }
+    /// Load a screen from the given path. Sugar for `load_from` with `File::open`.
+    pub fn load_file<P: AsRef<Path>>(path: P) -> io::Result<Scr> {
+        Scr::load_from(File::open(path)?)
+    }
+    /// Load a screen from any instance of the `Read` trait.
+    ///
+    /// Each distinct cell bitmap becomes a tile, starting from index 1 (index 0 is left blank).
+    /// A bitmap that is the inverse of one already seen reuses that tile with its colors swapped,
+    /// so at most 512 really different bitmaps can be loaded; screens with more are an error.
+    /// Flashing cells are loaded without flashing.
+    pub fn load_from<R: Read>(mut input: R) -> io::Result<Scr> {
+        let mut data = vec![0; SCR_SIZE];
+        input.read_exact(&mut data)?;
+        let mut tile_set = TileSet::blank();
+        let mut tiles : HashMap<u64, usize> = HashMap::new();
+        tiles.insert(0, 0);
+        let mut graphic = Graphic::blank(WIDTH, HEIGHT);
+        for y in 0..HEIGHT {
+            for x in 0..WIDTH {
+                let mut bitmap = 0;
+                for row in 0..8 {
+                    let b = data[bitmap_offset(x, y * 8 + row)].reverse_bits();
+                    bitmap |= (b as u64) << (row * 8);
+                }
+                let a = data[BITMAP_SIZE + (x + y * WIDTH) as usize] as usize;
+                let bright = if a & 0x40 != 0 { 8 } else { 0 };
+                let ink = SPECTRUM_PALETTE[bright | (a & 0x07)];
+                let paper = SPECTRUM_PALETTE[bright | ((a >> 3) & 0x07)];
+                let tile = if let Some(&index) = tiles.get(&bitmap) {
+                    Tile { index, fg: ink, bg: paper }
+                } else if let Some(&index) = tiles.get(&!bitmap) {
+                    Tile { index, fg: paper, bg: ink }
+                } else {
+                    let index = tiles.len();
+                    if index >= tile_set.len() {
+                        return Err(io::Error::new(io::ErrorKind::InvalidData, "screen has more than 512 different cells"));
+                    }
+                    tile_set[index] = bitmap;
+                    tiles.insert(bitmap, index);
+                    Tile { index, fg: ink, bg: paper }
+                };
+                graphic.set_tile(x, y, tile);
+            }
+        }
+        Ok(Scr { tile_set, graphic })
+    }
+    /// Save the top-left 32x24 tiles of the graphic as a screen to some instance of `Write` (such as a file).
+    ///
+    /// Each cell's colors are saved as the nearest pair of Spectrum colors with the same brightness.
+    /// Returns the positions of the cells whose colors could not be saved exactly, so that they can be
+    /// pointed out to the user.
+    pub fn save<W: Write>(&self, file: &mut W) -> io::Result<Vec<(u32, u32)>> {
+        let mut data = vec![0; SCR_SIZE];
+        let mut misfits = Vec::new();
+        for y in 0..HEIGHT.min(self.graphic.height) {
+            for x in 0..WIDTH.min(self.graphic.width) {
+                let t = self.graphic.get_tile(x, y);
+                let bitmap = if t.index < self.tile_set.len() { self.tile_set[t.index] } else { 0 };
+                for row in 0..8 {
+                    data[bitmap_offset(x, y * 8 + row)] = ((bitmap >> (row * 8)) as u8).reverse_bits();
+                }
+                let (a, exact) = attribute(t.fg, t.bg);
+                data[BITMAP_SIZE + (x + y * WIDTH) as usize] = a;
+                if !exact {
+                    misfits.push((x, y));
+                }
+            }
+        }
+        file.write_all(&data)?;
+        Ok(misfits)
+    }
+    /// Save as a screen at the provided path. Returns the cells whose colors could not be saved exactly, as `save` does.
+    pub fn store<P: AsRef<Path>>(&self, path: P) -> io::Result<Vec<(u32, u32)>> {
+        self.save(&mut File::create(path)?)
+    }
+}
@@ -0,0 +1,177 @@
+//! Stamps (or metatiles): small graphics, such as a 2x2 tree or a 3x2 door, that are placed
+//! as a unit to build larger graphics like levels.
+//!
+//! ```ignore
+//! let mut stamps = StampLibrary::new();
+//! stamps.capture("tree", &sketch, 0, 0, 2, 2);
+//! stamps.place("tree", &mut level, 10, 4);
+//! for p in stamps.find_placements(&level) {
+//!     println!("{} at {},{}", p.name, p.x, p.y);
+//! }
+//! ```
+//!
+//! A library of stamps is kept with the tile set they are drawn with in a `Bundle`, or can be
+//! saved on its own. A stamp file (`.tss`) starts with the magic number `tsrs` and a version
+//! byte, followed by the number of stamps and then each stamp's name and graphic (in the format
+//! of `Graphic::save_compressed`), each preceded by its length in bytes.
+
+use std::fs::File;
+use std::io;
+use std::io::{Cursor, Read, Write};
+use std::path::Path;
+use std::collections::BTreeMap;
+
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+
+use super::bundle::{read_section, read_string, write_section};
+use super::Graphic;
+
+const MAGIC: &[u8; 4] = b"tsrs";
+const VERSION: u8 = 1;
+
+/// Where a stamp appears in a graphic, as found by `StampLibrary::find_placements`.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Placement {
+    /// The name of the stamp.
+    pub name: String,
+    /// The position of the stamp's top-left tile in the graphic.
+    pub x: u32,
+    pub y: u32,
+}
+
+/// A collection of stamps, each a small graphic with a name.
+#[derive(Clone, Default)]
+pub struct StampLibrary {
+    stamps: BTreeMap<String, Graphic<()>>,
+}
+
+impl StampLibrary {
+    /// An empty library.
+    pub fn new() -> StampLibrary {
+        StampLibrary { stamps: BTreeMap::new() }
+    }
+    /// The number of stamps in the library.
+    pub fn len(&self) -> usize {
+        self.stamps.len()
+    }
+    /// Whether the library has no stamps.
+    pub fn is_empty(&self) -> bool {
+        self.stamps.is_empty()
+    }
+    /// The stamp with the given name, if there is one.
+    pub fn get(&self, name: &str) -> Option<&Graphic<()>> {
+        self.stamps.get(name)
+    }
+    /// The stamp with the given name, if there is one, for editing.
+    pub fn get_mut(&mut self, name: &str) -> Option<&mut Graphic<()>> {
+        self.stamps.get_mut(name)
+    }
+    /// The names of the stamps, in order.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.stamps.keys().map(|k| k.as_str())
+    }
+    /// The stamps with their names, in order of name.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &Graphic<()>)> {
+        self.stamps.iter().map(|(k, g)| (k.as_str(), g))
+    }
+    /// Add a copy of a graphic to the library as a stamp with the given name,
+    /// returning the stamp it replaces, if any.
+    pub fn insert<T, S: Into<String>>(&mut self, name: S, graphic: &Graphic<T>) -> Option<Graphic<()>> {
+        self.capture(name, graphic, 0, 0, graphic.width, graphic.height)
+    }
+    /// Add the rectangle of a graphic with its top-left corner at `(x, y)` to the library as a stamp
+    /// with the given name, returning the stamp it replaces, if any. The rectangle is clipped to the graphic.
+    pub fn capture<T, S: Into<String>>(&mut self, name: S, graphic: &Graphic<T>, x: u32, y: u32, width: u32, height: u32) -> Option<Graphic<()>> {
+        let width = width.min(graphic.width.saturating_sub(x));
+        let height = height.min(graphic.height.saturating_sub(y));
+        let mut g = Graphic::blank(width, height);
+        g.copy_tiles_from(graphic, x, y, width, height, 0, 0);
+        self.stamps.insert(name.into(), g)
+    }
+    /// Remove the stamp with the given name from the library, returning it if there was one.
+    pub fn remove(&mut self, name: &str) -> Option<Graphic<()>> {
+        self.stamps.remove(name)
+    }
+    /// Place the stamp with the given name in a graphic with its top-left corner at `(x, y)`.
+    /// Any part of the stamp outside the graphic is left out.
+    /// Returns whether there is a stamp with that name.
+    pub fn place<T>(&self, name: &str, graphic: &mut Graphic<T>, x: u32, y: u32) -> bool {
+        match self.stamps.get(name) {
+            Some(stamp) => {
+                graphic.copy_all_tiles_from(stamp, x, y);
+                true
+            }
+            None => false,
+        }
+    }
+    /// Find every place in a graphic where one of the stamps appears, with the same tiles and colors,
+    /// in order of position, row by row. Stamps may overlap, and an empty stamp is never found.
+    pub fn find_placements<T>(&self, graphic: &Graphic<T>) -> Vec<Placement> {
+        let mut placements = Vec::new();
+        for y in 0..graphic.height {
+            for x in 0..graphic.width {
+                for (name, stamp) in &self.stamps {
+                    if stamp.tiles.is_empty() || !graphic.contains_at(stamp, x, y) {
+                        continue;
+                    }
+                    placements.push(Placement { name: name.clone(), x, y });
+                }
+            }
+        }
+        placements
+    }
+    /// Load a stamp library from the file with the given path. Sugar for `load_from` with `File::open`.
+    pub fn load_file<P: AsRef<Path>>(path: P) -> io::Result<StampLibrary> {
+        StampLibrary::load_from(File::open(path)?)
+    }
+    /// Load a stamp library from any instance of the `Read` trait.
+    pub fn load_from<R: Read>(mut input: R) -> io::Result<StampLibrary> {
+        let mut magic = [0; 4];
+        input.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "not a tesserae stamp library"));
+        }
+        if input.read_u8()? != VERSION {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "unsupported stamp library version"));
+        }
+        let mut stamps = BTreeMap::new();
+        for _ in 0..input.read_u32::<LittleEndian>()? {
+            let name = read_string(&mut input)?;
+            stamps.insert(name, Graphic::load_from(Cursor::new(read_section(&mut input)?))?);
+        }
+        Ok(StampLibrary { stamps })
+    }
+    /// Save the stamp library to some instance of `Write` (such as a file).
+    pub fn save<W: Write>(&self, file: &mut W) -> io::Result<()> {
+        file.write_all(MAGIC)?;
+        file.write_u8(VERSION)?;
+        file.write_u32::<LittleEndian>(self.stamps.len() as u32)?;
+        let mut data = Vec::new();
+        for (name, stamp) in &self.stamps {
+            write_section(file, name.as_bytes())?;
+            data.clear();
+            stamp.save_compressed(&mut data)?;
+            write_section(file, &data)?;
+        }
+        Ok(())
+    }
+    /// Save the stamp library to a file at the provided path.
+    pub fn store<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        self.save(&mut File::create(path)?)
+    }
+}
+
+impl<T> Graphic<T> {
+    /// Whether another graphic (such as a stamp) appears in this one with its top-left corner at `(x, y)`,
+    /// with the same tiles and colors. A graphic that would extend past the edges of this one doesn't appear in it.
+    pub fn contains_at<U>(&self, other: &Graphic<U>, x: u32, y: u32) -> bool {
+        if x.saturating_add(other.width) > self.width || y.saturating_add(other.height) > self.height {
+            return false;
+        }
+        (0..other.height).all(|j| {
+            let start = (x + (y + j) * self.width) as usize;
+            let row = (j * other.width) as usize;
+            self.tiles[start..start + other.width as usize] == other.tiles[row..row + other.width as usize]
+        })
+    }
+}
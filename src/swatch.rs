@@ -0,0 +1,109 @@
+use std::fs::File;
+use std::path::Path;
+use std::ops::{Index, IndexMut};
+use std::io;
+use std::io::{Read,Write,Cursor};
+use super::Color;
+use byteorder::{ReadBytesExt,WriteBytesExt};
+
+const SWATCH_SIZE: usize = 256;
+
+/// A palette of 256 colors, such as the one used by the tesseraed editor.
+/// 
+/// Indexing with a `usize` gives the color at that position, which can also be
+/// changed by assigning to that index.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Swatch {
+    data: Vec<Color>,
+}
+impl Index<usize> for Swatch {
+    type Output = Color;
+    fn index(&self, index: usize) -> &Color {
+        &self.data[index]
+    }
+}
+impl IndexMut<usize> for Swatch {
+    fn index_mut(&mut self, index: usize) -> &mut Color {
+        &mut self.data[index]
+    }
+}
+impl Default for Swatch {
+    /// The built-in swatch used by the tesseraed editor.
+    fn default() -> Swatch {
+        let f = include_bytes!("../swatch");
+        Swatch::load_from(Cursor::new(&f[..]))
+    }
+}
+impl Swatch {
+    fn new() -> Swatch {
+        Swatch {
+            data: Vec::new(),
+        }
+    }
+    /// Create a swatch starting with the given colors. Any further colors are fully transparent,
+    /// and any colors beyond the 256th are ignored.
+    pub fn from_colors(colors: &[Color]) -> Swatch {
+        let mut ts = Swatch::new();
+        ts.data.extend(colors.iter().take(SWATCH_SIZE));
+        ts.data.resize(SWATCH_SIZE, Color::RGBA(0,255,0,0));
+        ts
+    }
+    fn read_color<R:Read>(mut f : R) -> io::Result<Color> {
+        let r = f.read_u8()?;
+        let g = f.read_u8()?;
+        let b = f.read_u8()?; 
+        let a = f.read_u8()?;        
+        Ok(Color::RGBA(r,g,b,a))
+    }
+    
+    /// Load a swatch from a `Read` instance such as a file, as four bytes (red, green, blue, alpha)
+    /// per color. If there are fewer than 256 colors, the rest are fully transparent.
+    pub fn load_from<R: Read>(mut input : R) -> Swatch {
+        let mut ts = Swatch::new();
+        let mut c = 0;
+        while let Ok(u) = Swatch::read_color(&mut input) {
+            if c >= SWATCH_SIZE { break } else { ts.data.push(u); c += 1}
+        }
+        while c < SWATCH_SIZE {
+            c += 1;
+            ts.data.push(Color::RGBA(0,255,0,0));
+        }
+        ts
+    }
+    /// Load a swatch from the file with the given path. Sugar for `load_from` with `File::open`.
+    pub fn load_file<P: AsRef<Path>>(path : P) -> io::Result<Swatch> {
+        let f = File::open(path)?;
+        Ok(Swatch::load_from(f))
+    }
+    /// Save the swatch to some instance of `Write` (such as a file), using the same file format used in `load_from`.
+    pub fn save<W: Write>(&self, file: &mut W) -> io::Result<()> {
+        for i in &self.data {
+            file.write_u8(i.r)?;
+            file.write_u8(i.g)?;
+            file.write_u8(i.b)?;
+            file.write_u8(i.a)?;
+        }
+        Ok(())
+    }
+    /// Save the swatch to a file at the provided path.
+    pub fn store<P: AsRef<Path>>(&self, path : P) -> io::Result<()> {
+        self.save(&mut File::create(path)?)
+    }
+    /// The number of colors in the swatch. Swatches are filled out with transparent colors
+    /// (or cut short) to 256 colors when they are made or loaded, so this is 256.
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+    /// Whether the swatch has no colors, which is never the case.
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+    /// The index of the color in the first `limit` colors of the swatch that is nearest to the given color.
+    pub fn nearest(&self, color: Color, limit: usize) -> usize {
+        let distance = |p: &Color| {
+            let (dr, dg, db, da) = (p.r as i32 - color.r as i32, p.g as i32 - color.g as i32, p.b as i32 - color.b as i32, p.a as i32 - color.a as i32);
+            dr * dr + dg * dg + db * db + da * da
+        };
+        (0..limit.min(self.data.len())).min_by_key(|&i| distance(&self.data[i])).unwrap_or(0)
+    }
+}
\ No newline at end of file
@@ -0,0 +1,546 @@
+//! Teletext (and BBC Micro Mode 7) pages: a built-in teletext tile set, and loading and saving
+//! pages of 40x25 characters with their control codes, either raw or in the `.tti` format used
+//! by teletext editors and inserters.
+//!
+//! Teletext has no attributes: colors, mosaic graphics and double height are switched on by
+//! control codes, which take up a character cell of their own and usually show as a space.
+//! `Page::to_graphic` decodes them into tiles from `TileSet::teletext`, which is laid out as follows:
+//!
+//! | Tiles   | Contents                                                              |
+//! |---------|-----------------------------------------------------------------------|
+//! | 0-127   | Alphanumeric characters at their codes (0-31 are blank)               |
+//! | 128-191 | Contiguous mosaics, by sextant pattern                                |
+//! | 192-255 | Separated mosaics, by sextant pattern                                 |
+//! | 256-351 | Top halves of double height alphanumerics 32-127                     |
+//! | 352-447 | Bottom halves of double height alphanumerics 32-127                  |
+//! | 448-511 | Halves of double height mosaics: contiguous tops and bottoms, then separated tops and bottoms, 16 of each |
+//!
+//! Mosaic sextant patterns are numbered as in the `semigraphics` module, so bit `x + y * 2`
+//! is the block at `(x,y)`. A double height mosaic top is numbered by the four blocks of its top two
+//! rows of blocks, and a bottom by the four blocks of its bottom two rows.
+
+use std::fs::File;
+use std::io;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::Path;
+
+use super::semigraphics::BlockResolution;
+use super::{Color, Graphic, Swatch, Tile, TileSet};
+
+const WIDTH: u32 = 40;
+const HEIGHT: u32 = 25;
+const PAGE_SIZE: usize = (WIDTH * HEIGHT) as usize;
+
+const CONTIGUOUS: usize = 128;
+const SEPARATED: usize = 192;
+const DOUBLE_TOP: usize = 256;
+const DOUBLE_BOTTOM: usize = 352;
+const DOUBLE_MOSAIC: usize = 448;
+
+/// The eight teletext colors, in the order of their control codes
+/// (black, red, green, yellow, blue, magenta, cyan, white).
+pub const TELETEXT_PALETTE: [Color; 8] = [
+    Color { r: 0, g: 0, b: 0, a: 255 },
+    Color { r: 255, g: 0, b: 0, a: 255 },
+    Color { r: 0, g: 255, b: 0, a: 255 },
+    Color { r: 255, g: 255, b: 0, a: 255 },
+    Color { r: 0, g: 0, b: 255, a: 255 },
+    Color { r: 255, g: 0, b: 255, a: 255 },
+    Color { r: 0, g: 255, b: 255, a: 255 },
+    Color { r: 255, g: 255, b: 255, a: 255 },
+];
+
+/// Characters of the English teletext character set that differ from ASCII,
+/// and the code page 437 characters used to draw them.
+const NATIONAL: [(u8, u8); 12] = [
+    (0x23, 156), (0x5B, 27), (0x5C, 171), (0x5D, 26), (0x5E, 24), (0x5F, b'#'),
+    (0x60, 196), (0x7B, 172), (0x7C, 186), (0x7D, 243), (0x7E, 246), (0x7F, 219),
+];
+
+/// Remove the gaps between the blocks of separated mosaics: the right column of
+/// each block and the bottom row of each row of blocks.
+fn separate(data: u64) -> u64 {
+    let mut mask = 0;
+    for row in [0, 1, 3, 5, 6].iter() {
+        mask |= 0x77 << (row * 8);
+    }
+    data & mask
+}
+
+/// Stretch the top or bottom half of a tile to fill the whole tile.
+fn stretch(data: u64, bottom: bool) -> u64 {
+    let start = if bottom { 4 } else { 0 };
+    let mut out = 0;
+    for row in 0..8 {
+        out |= ((data >> ((start + row / 2) * 8)) & 0xFF) << (row * 8);
+    }
+    out
+}
+
+impl TileSet {
+    /// A built-in teletext tile set, made from the `cga_ascii` characters and generated mosaics.
+    /// See the `teletext` module for its layout. The character map maps ASCII to the alphanumerics.
+    pub fn teletext() -> TileSet {
+        let cga = TileSet::cga_ascii();
+        let mut ts = TileSet::blank();
+        for c in 32..128 {
+            ts[c] = cga[c];
+        }
+        for &(c, cp) in NATIONAL.iter() {
+            ts[c as usize] = cga[cp as usize];
+        }
+        for p in 0..64 {
+            let data = BlockResolution::Sextants.tile_data(p as u8);
+            ts[CONTIGUOUS + p] = data;
+            ts[SEPARATED + p] = separate(data);
+        }
+        for c in 32..128 {
+            ts[DOUBLE_TOP + c - 32] = stretch(ts[c], false);
+            ts[DOUBLE_BOTTOM + c - 32] = stretch(ts[c], true);
+        }
+        for half in 0..16 {
+            let top = BlockResolution::Sextants.tile_data(half as u8);
+            let bottom = BlockResolution::Sextants.tile_data((half << 2) as u8);
+            ts[DOUBLE_MOSAIC + half] = stretch(top, false);
+            ts[DOUBLE_MOSAIC + 16 + half] = stretch(bottom, true);
+            ts[DOUBLE_MOSAIC + 32 + half] = stretch(separate(top), false);
+            ts[DOUBLE_MOSAIC + 48 + half] = stretch(separate(bottom), true);
+        }
+        for c in 0..256 {
+            ts.char_map[c] = if (32..127).contains(&c) { c } else { 0 };
+        }
+        ts['#'] = 0x5F;
+        ts
+    }
+}
+
+/// The sextant pattern of a mosaic character code.
+fn mosaic_pattern(code: u8) -> u8 {
+    (code & 0x1F) | ((code & 0x40) >> 1)
+}
+
+/// The mosaic character code of a sextant pattern.
+fn mosaic_code(pattern: u8) -> u8 {
+    0x20 | (pattern & 0x1F) | ((pattern & 0x20) << 1)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Glyph {
+    /// An alphanumeric character code, from 0x20 to 0x7F.
+    Alpha(u8),
+    /// A sextant pattern, and whether it is separated.
+    Mosaic(u8, bool),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Size {
+    Normal,
+    Top,
+    Bottom,
+}
+
+fn tile_index(glyph: Glyph, size: Size) -> usize {
+    match (glyph, size) {
+        (Glyph::Alpha(c), Size::Normal) => c as usize,
+        (Glyph::Alpha(c), Size::Top) => DOUBLE_TOP + c as usize - 32,
+        (Glyph::Alpha(c), Size::Bottom) => DOUBLE_BOTTOM + c as usize - 32,
+        (Glyph::Mosaic(p, s), Size::Normal) => (if s { SEPARATED } else { CONTIGUOUS }) + p as usize,
+        (Glyph::Mosaic(p, s), Size::Top) => DOUBLE_MOSAIC + (if s { 32 } else { 0 }) + (p & 0x0F) as usize,
+        (Glyph::Mosaic(p, s), Size::Bottom) => DOUBLE_MOSAIC + (if s { 48 } else { 16 }) + (p >> 2) as usize,
+    }
+}
+
+/// The glyph shown by a tile of the teletext tile set. Halves of double height mosaics give
+/// the part of the pattern they show, with the rest of the blocks unlit.
+fn tile_glyph(index: usize) -> (Glyph, Size) {
+    match index {
+        0..=31 => (Glyph::Alpha(0x20), Size::Normal),
+        32..=127 => (Glyph::Alpha(index as u8), Size::Normal),
+        128..=255 => (Glyph::Mosaic((index & 0x3F) as u8, index >= SEPARATED), Size::Normal),
+        256..=351 => (Glyph::Alpha((index - DOUBLE_TOP + 32) as u8), Size::Top),
+        352..=447 => (Glyph::Alpha((index - DOUBLE_BOTTOM + 32) as u8), Size::Bottom),
+        448..=511 => {
+            let half = (index & 0x0F) as u8;
+            let separated = index - DOUBLE_MOSAIC >= 32;
+            if (index - DOUBLE_MOSAIC) & 0x10 == 0 {
+                (Glyph::Mosaic(half, separated), Size::Top)
+            } else {
+                (Glyph::Mosaic(half << 2, separated), Size::Bottom)
+            }
+        }
+        _ => (Glyph::Alpha(0x20), Size::Normal),
+    }
+}
+
+/// A decoded character cell: its glyph, whether it is double height, and its colors.
+#[derive(Clone, Copy, Debug)]
+struct Cell {
+    glyph: Glyph,
+    double: bool,
+    fg: u8,
+    bg: u8,
+}
+
+/// Decode a row of characters and control codes, returning its cells and whether any are double height.
+fn decode_row(row: &[u8]) -> (Vec<Cell>, bool) {
+    let (mut fg, mut bg) = (7, 0);
+    let (mut graphics, mut separated, mut double, mut hold) = (false, false, false, false);
+    let mut held = None;
+    let mut any_double = false;
+    let mut cells = Vec::with_capacity(row.len());
+    for &b in row {
+        let c = b & 0x7F;
+        // "Set-at" codes take effect in their own cell.
+        match c {
+            0x0C => { double = false; held = None }
+            0x19 => separated = false,
+            0x1A => separated = true,
+            0x1C => bg = 0,
+            0x1D => bg = fg,
+            0x1E => hold = true,
+            _ => {}
+        }
+        let glyph = if c < 0x20 {
+            match held {
+                Some(g) if hold && graphics => g,
+                _ => Glyph::Alpha(0x20),
+            }
+        } else if graphics && c & 0x20 != 0 {
+            let g = Glyph::Mosaic(mosaic_pattern(c), separated);
+            held = Some(g);
+            g
+        } else {
+            Glyph::Alpha(c)
+        };
+        cells.push(Cell { glyph, double, fg, bg });
+        // "Set-after" codes take effect from the next cell.
+        match c {
+            0x00..=0x07 => {
+                if graphics { held = None }
+                fg = c;
+                graphics = false;
+            }
+            0x0D => {
+                if !double { held = None }
+                double = true;
+                any_double = true;
+            }
+            0x10..=0x17 => {
+                if !graphics { held = None }
+                fg = c & 0x07;
+                graphics = true;
+            }
+            0x1F => hold = false,
+            _ => {}
+        }
+    }
+    (cells, any_double)
+}
+
+/// The state of the decoder that the encoder keeps track of.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct State {
+    fg: u8,
+    bg: u8,
+    graphics: bool,
+    separated: bool,
+    double: bool,
+}
+
+/// A cell that the encoder is trying to produce. Blank cells have no glyph, and only their background matters.
+#[derive(Clone, Copy, Debug)]
+struct Target {
+    glyph: Option<Glyph>,
+    double: bool,
+    fg: u8,
+    bg: u8,
+}
+
+impl State {
+    /// Whether a target can be shown by a single character without any control codes.
+    fn fits(&self, t: &Target) -> bool {
+        self.bg == t.bg && match t.glyph {
+            None => true,
+            Some(g) => t.fg == self.fg && t.double == self.double && match g {
+                // Capital letters "blast through" mosaics.
+                Glyph::Alpha(c) => !self.graphics || c & 0x20 == 0,
+                Glyph::Mosaic(_, s) => self.graphics && s == self.separated,
+            },
+        }
+    }
+    /// The control code that gets closest to showing the target, if any are needed.
+    fn code_towards(&self, t: &Target) -> Option<u8> {
+        if t.bg != self.bg {
+            if t.bg == 0 { return Some(0x1C) }
+            if self.fg == t.bg { return Some(0x1D) }
+            // New background takes the foreground color, so that has to be set first.
+            return Some(t.bg | if self.graphics { 0x10 } else { 0x00 });
+        }
+        let g = t.glyph?;
+        if t.double != self.double {
+            return Some(if t.double { 0x0D } else { 0x0C });
+        }
+        let graphics = match g {
+            Glyph::Alpha(c) => self.graphics && c & 0x20 == 0,
+            Glyph::Mosaic(..) => true,
+        };
+        if t.fg != self.fg || graphics != self.graphics {
+            return Some(t.fg | if graphics { 0x10 } else { 0x00 });
+        }
+        match g {
+            Glyph::Mosaic(_, s) if s != self.separated => Some(if s { 0x1A } else { 0x19 }),
+            _ => None,
+        }
+    }
+    fn apply(&mut self, code: u8) {
+        match code {
+            0x00..=0x07 => { self.fg = code; self.graphics = false }
+            0x0C => self.double = false,
+            0x0D => self.double = true,
+            0x10..=0x17 => { self.fg = code & 0x07; self.graphics = true }
+            0x19 => self.separated = false,
+            0x1A => self.separated = true,
+            0x1C => self.bg = 0,
+            0x1D => self.bg = self.fg,
+            _ => {}
+        }
+    }
+}
+
+/// Encode a row of targets as characters and control codes. Control codes go in blank cells where
+/// possible, preparing for the next cell that needs them; otherwise they replace the cell that needs them.
+fn encode_row(targets: &[Target]) -> Vec<u8> {
+    let mut state = State { fg: 7, bg: 0, graphics: false, separated: false, double: false };
+    let mut row = Vec::with_capacity(targets.len());
+    for (x, t) in targets.iter().enumerate() {
+        let code = if state.fits(t) {
+            if t.glyph.is_none() {
+                // A spare cell: prepare for the next character or background change, as long as this cell still shows correctly.
+                targets[x + 1..].iter().find(|n| n.glyph.is_some() || !state.fits(n)).and_then(|n| {
+                    let code = state.code_towards(n)?;
+                    let mut after = state;
+                    after.apply(code);
+                    // Background changes take effect in this cell, so it must still look right.
+                    if after.bg != t.bg { None } else { Some(code) }
+                })
+            } else {
+                None
+            }
+        } else {
+            state.code_towards(t)
+        };
+        match code {
+            Some(code) => {
+                state.apply(code);
+                row.push(code);
+            }
+            None => row.push(match t.glyph {
+                Some(Glyph::Alpha(c)) => c,
+                Some(Glyph::Mosaic(p, _)) => mosaic_code(p),
+                None => 0x20,
+            }),
+        }
+    }
+    row
+}
+
+/// Whether two tiles look the same.
+fn same_appearance(a: Tile, b: Tile, tile_set: &TileSet, palette: &Swatch) -> bool {
+    let data = |t: Tile| if t.index < tile_set.len() { tile_set[t.index] } else { 0 };
+    let (da, db) = (data(a), data(b));
+    let (afg, abg) = (palette.nearest(a.fg, 8), palette.nearest(a.bg, 8));
+    let (bfg, bbg) = (palette.nearest(b.fg, 8), palette.nearest(b.bg, 8));
+    (0..64).all(|i| {
+        let ca = if da & (1 << i) != 0 { afg } else { abg };
+        let cb = if db & (1 << i) != 0 { bfg } else { bbg };
+        ca == cb
+    })
+}
+
+/// A teletext page of 25 rows of 40 characters, including the header row.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Page {
+    /// 1000 seven-bit character codes, row by row. Codes below 32 are control codes.
+    pub data: Vec<u8>,
+}
+
+impl Page {
+    /// A page full of spaces.
+    pub fn blank() -> Page {
+        Page { data: vec![0x20; PAGE_SIZE] }
+    }
+    /// Load a page from the file with the given path. Files ending in `.tti` or `.ttix`
+    /// are loaded with `load_tti_from` and any others with `load_from`.
+    pub fn load_file<P: AsRef<Path>>(path: P) -> io::Result<Page> {
+        let tti = path.as_ref().extension().is_some_and(|e| e.eq_ignore_ascii_case("tti") || e.eq_ignore_ascii_case("ttix"));
+        let f = File::open(path)?;
+        if tti { Page::load_tti_from(f) } else { Page::load_from(f) }
+    }
+    /// Load a raw page from a `Read` instance such as a file: 40 bytes for each row, with parity
+    /// bits (if any) ignored. Pages of 24 rows are also accepted, leaving the last row blank.
+    pub fn load_from<R: Read>(mut input: R) -> io::Result<Page> {
+        let mut data = Vec::new();
+        input.read_to_end(&mut data)?;
+        if data.len() < PAGE_SIZE - WIDTH as usize {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "teletext page is shorter than 24 rows"));
+        }
+        let mut page = Page::blank();
+        for (p, d) in page.data.iter_mut().zip(&data) {
+            *p = d & 0x7F;
+        }
+        Ok(page)
+    }
+    /// Load a page from a `.tti` file, taking the rows from its `OL` lines and ignoring the rest.
+    /// Control codes may be written as themselves, with the top bit set, or escaped
+    /// (as ESC followed by the code plus 0x40).
+    pub fn load_tti_from<R: Read>(input: R) -> io::Result<Page> {
+        let mut page = Page::blank();
+        for line in BufReader::new(input).split(b'\n') {
+            let mut line = line?;
+            if line.last() == Some(&b'\r') {
+                line.pop();
+            }
+            if !line.starts_with(b"OL,") {
+                continue;
+            }
+            let rest = &line[3..];
+            let comma = match rest.iter().position(|&b| b == b',') {
+                Some(c) => c,
+                None => continue,
+            };
+            let row : usize = match std::str::from_utf8(&rest[..comma]).ok().and_then(|s| s.trim().parse().ok()) {
+                Some(r) if r < HEIGHT as usize => r,
+                _ => continue,
+            };
+            let mut codes = Vec::with_capacity(WIDTH as usize);
+            let mut bytes = rest[comma + 1..].iter();
+            while let Some(&b) = bytes.next() {
+                codes.push(if b == 0x1B {
+                    bytes.next().map_or(0x20, |&e| e.wrapping_sub(0x40) & 0x7F)
+                } else {
+                    b & 0x7F
+                });
+            }
+            codes.resize(WIDTH as usize, 0x20);
+            let start = row * WIDTH as usize;
+            page.data[start..start + WIDTH as usize].copy_from_slice(&codes);
+        }
+        Ok(page)
+    }
+    /// Save the raw page (1000 bytes, without parity) to some instance of `Write`.
+    pub fn save<W: Write>(&self, file: &mut W) -> io::Result<()> {
+        file.write_all(&self.data)
+    }
+    /// Save the page as a `.tti` file, as page 100 with control codes escaped.
+    /// Rows that are entirely spaces are left out.
+    pub fn save_tti<W: Write>(&self, file: &mut W) -> io::Result<()> {
+        file.write_all(b"PN,10000\r\nSC,0000\r\nPS,8000\r\n")?;
+        for (y, row) in self.data.chunks(WIDTH as usize).enumerate() {
+            if row.iter().all(|&c| c == 0x20) {
+                continue;
+            }
+            write!(file, "OL,{},", y)?;
+            for &c in row {
+                if c < 0x20 {
+                    file.write_all(&[0x1B, c + 0x40])?;
+                } else {
+                    file.write_all(&[c])?;
+                }
+            }
+            file.write_all(b"\r\n")?;
+        }
+        Ok(())
+    }
+    /// Save the page as a `.tti` file at the provided path.
+    pub fn store<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        self.save_tti(&mut File::create(path)?)
+    }
+    /// Decode the page into a 40x25 graphic using `TileSet::teletext`, with the teletext colors.
+    ///
+    /// Both black foreground codes (0x00 and 0x10) are supported, and concealed and flashing text is shown steadily.
+    /// As on a real decoder, the row after one with any double height characters shows the bottom halves of that row.
+    pub fn to_graphic(&self) -> Graphic<()> {
+        let mut g = Graphic::blank(WIDTH, HEIGHT);
+        let mut y = 0;
+        while y < HEIGHT {
+            let start = (y * WIDTH) as usize;
+            let (cells, double) = decode_row(&self.data[start..start + WIDTH as usize]);
+            for (x, c) in cells.iter().enumerate() {
+                let (fg, bg) = (TELETEXT_PALETTE[c.fg as usize], TELETEXT_PALETTE[c.bg as usize]);
+                let size = if c.double { Size::Top } else { Size::Normal };
+                g.set_tile(x as u32, y, Tile { index: tile_index(c.glyph, size), fg, bg });
+                if double && y + 1 < HEIGHT {
+                    let bottom = if c.double { tile_index(c.glyph, Size::Bottom) } else { 0x20 };
+                    g.set_tile(x as u32, y + 1, Tile { index: bottom, fg, bg });
+                }
+            }
+            y += if double { 2 } else { 1 };
+        }
+        g
+    }
+    /// Encode the top-left 40x25 tiles of a graphic drawn with `TileSet::teletext` as a page,
+    /// using the nearest teletext colors.
+    ///
+    /// Control codes are fitted into blank cells where possible, but as they take up cells of their own,
+    /// not every graphic can be encoded exactly. Returns the page along with the positions of the cells
+    /// that will look different, so that they can be pointed out to the user.
+    pub fn from_graphic<T>(graphic: &Graphic<T>) -> (Page, Vec<(u32, u32)>) {
+        let tile_set = TileSet::teletext();
+        let palette = Swatch::from_colors(&TELETEXT_PALETTE);
+        let tile = |x: u32, y: u32| {
+            if x < graphic.width && y < graphic.height {
+                graphic.get_tile(x, y)
+            } else {
+                Tile { index: 0x20, fg: TELETEXT_PALETTE[7], bg: TELETEXT_PALETTE[0] }
+            }
+        };
+        let mut page = Page::blank();
+        let mut y = 0;
+        while y < HEIGHT {
+            let double = (0..WIDTH).any(|x| tile_glyph(tile(x, y).index).1 == Size::Top);
+            let targets : Vec<Target> = (0..WIDTH).map(|x| {
+                let t = tile(x, y);
+                let (mut glyph, size) = tile_glyph(t.index);
+                if let (Glyph::Mosaic(p, s), Size::Top) = (glyph, size) {
+                    // The bottom row of blocks is shown by the tile below.
+                    if let (Glyph::Mosaic(q, r), Size::Bottom) = tile_glyph(tile(x, y + 1).index) {
+                        if r == s { glyph = Glyph::Mosaic(p | (q & 0x30), s) }
+                    }
+                }
+                let blank = t.index >= tile_set.len() || tile_set[t.index] == 0;
+                Target {
+                    glyph: if blank { None } else { Some(glyph) },
+                    double: size == Size::Top,
+                    fg: palette.nearest(t.fg, 8) as u8,
+                    bg: palette.nearest(t.bg, 8) as u8,
+                }
+            }).collect();
+            let row = encode_row(&targets);
+            let start = (y * WIDTH) as usize;
+            page.data[start..start + WIDTH as usize].copy_from_slice(&row);
+            if double && y + 1 < HEIGHT {
+                // The row below a double height row isn't shown, but repeating it helps decoders that don't support double height.
+                page.data.copy_within(start..start + WIDTH as usize, start + WIDTH as usize);
+            }
+            y += if double { 2 } else { 1 };
+        }
+        let decoded = page.to_graphic();
+        let mut misfits = Vec::new();
+        for y in 0..HEIGHT.min(graphic.height) {
+            for x in 0..WIDTH.min(graphic.width) {
+                if !same_appearance(graphic.get_tile(x, y), decoded.get_tile(x, y), &tile_set, &palette) {
+                    misfits.push((x, y));
+                }
+            }
+        }
+        (page, misfits)
+    }
+}
+
+impl Graphic<()> {
+    /// Load a teletext page from the file at the given path as a graphic using `TileSet::teletext`.
+    /// Sugar for `Page::load_file` followed by `Page::to_graphic`.
+    pub fn load_teletext_file<P: AsRef<Path>>(path: P) -> io::Result<Graphic<()>> {
+        Ok(Page::load_file(path)?.to_graphic())
+    }
+}
@@ -0,0 +1,308 @@
+//! A backend that draws graphics to a terminal using 24-bit ANSI escape sequences,
+//! so that programs written with tesserae can run in a terminal (e.g. over SSH).
+//!
+//! It works much like the SDL backend: a `Graphic<TerminalTexture>` renders its tiles
+//! into terminal character cells with `update_texture`, and `draw` copies those cells
+//! onto a `TerminalCanvas`. Calling `present` on the canvas then writes only the
+//! cells that have changed since the last time it was presented, moving the cursor
+//! as little as possible.
+//!
+//! ```ignore
+//! let mut canvas = TerminalCanvas::new(io::stdout(), 80, 25);
+//! let mut g = Graphic::blank(80,25).terminal_textured(TerminalMode::Characters);
+//! g.draw_text("hello", &tile_set, 0, 0, fg, bg);
+//! g.update_texture(&tile_set);
+//! g.draw(&mut canvas, (0,0));
+//! canvas.present()?;
+//! ```
+
+use std::io;
+use std::io::Write;
+
+use super::{cp437, semigraphics, Backend, Color, Draw, Graphic, Tile, TileSet, TileSetVersion};
+
+/// How tiles are shown in the terminal.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum TerminalMode {
+    /// Each tile is shown as a single character, found using the tile set's
+    /// character map (read as code page 437). Tiles with no character are shown
+    /// as the Unicode quadrant block character closest to their pixels.
+    Characters,
+    /// Each tile is shown pixel for pixel, 8 characters wide and 4 high, using
+    /// Unicode half block characters.
+    HalfBlocks,
+}
+
+impl TerminalMode {
+    /// The number of terminal characters across and down used to show each tile.
+    pub fn cell_size(self) -> (u32, u32) {
+        match self {
+            TerminalMode::Characters => (1, 1),
+            TerminalMode::HalfBlocks => (8, 4),
+        }
+    }
+}
+
+const QUADRANTS: [char; 16] = [
+    ' ', '▘', '▝', '▀', '▖', '▌', '▞', '▛', '▗', '▚', '▐', '▜', '▄', '▙', '▟', '█',
+];
+
+/// A single character in the terminal, with its colors. `None` is the terminal's default color.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct TerminalCell {
+    ch: char,
+    fg: Option<Color>,
+    bg: Option<Color>,
+}
+
+impl Default for TerminalCell {
+    fn default() -> TerminalCell {
+        TerminalCell { ch: ' ', fg: None, bg: None }
+    }
+}
+
+/// Fully transparent colors are left as the terminal default.
+fn terminal_color(c: Color) -> Option<Color> {
+    if c.a == 0 { None } else { Some(c) }
+}
+
+fn write_color<W: Write>(out: &mut W, ground: u8, c: Option<Color>) -> io::Result<()> {
+    match c {
+        Some(c) => write!(out, "\x1b[{}8;2;{};{};{}m", ground, c.r, c.g, c.b),
+        None => write!(out, "\x1b[{}9m", ground),
+    }
+}
+
+/// For each tile index, the character used to show it, if the character map has one.
+fn tile_characters(tile_set: &TileSet) -> Vec<Option<char>> {
+    tile_set.reverse_char_map().into_iter().map(|c| c.map(|c| cp437::to_char(c as u8))).collect()
+}
+
+/// The quadrant block character closest to the given tile data.
+fn quadrant_character(data: u64) -> char {
+    QUADRANTS[semigraphics::nearest_quadrants(data) as usize]
+}
+
+fn render_tile(mode: TerminalMode, tile: Tile, tile_set: &TileSet, chars: &[Option<char>], out: &mut [TerminalCell], stride: usize) {
+    let fg = terminal_color(tile.fg);
+    let bg = terminal_color(tile.bg);
+    let data = if tile.index < tile_set.len() { tile_set[tile.index] } else { 0 };
+    match mode {
+        TerminalMode::Characters => {
+            let ch = chars.get(tile.index).cloned().flatten().unwrap_or_else(|| quadrant_character(data));
+            out[0] = TerminalCell { ch, fg, bg };
+        }
+        TerminalMode::HalfBlocks => {
+            for y in 0..4 {
+                for x in 0..8 {
+                    let top = if data & (1 << (x + y * 16)) != 0 { fg } else { bg };
+                    let bottom = if data & (1 << (x + y * 16 + 8)) != 0 { fg } else { bg };
+                    out[x + y * stride] = match (top, bottom) {
+                        (t, b) if t == b => TerminalCell { ch: ' ', fg: None, bg: t },
+                        (None, b) => TerminalCell { ch: '▄', fg: b, bg: None },
+                        (t, b) => TerminalCell { ch: '▀', fg: t, bg: b },
+                    };
+                }
+            }
+        }
+    }
+}
+
+/// A terminal screen that graphics can be drawn to, analogous to an SDL `Canvas`.
+///
+/// Drawing only changes an internal buffer. Nothing is written until `present` is called,
+/// which writes only the characters that differ from what was last presented.
+pub struct TerminalCanvas<W: Write> {
+    out: W,
+    width: u32,
+    height: u32,
+    back: Vec<TerminalCell>,
+    front: Vec<Option<TerminalCell>>,
+}
+
+impl <W: Write> TerminalCanvas<W> {
+    /// Create a canvas of the given size in characters, writing to the given output
+    /// (typically `io::stdout()`). The first `present` writes every character.
+    pub fn new(out: W, width: u32, height: u32) -> TerminalCanvas<W> {
+        let size = (width * height) as usize;
+        TerminalCanvas {
+            out,
+            width,
+            height,
+            back: vec![Default::default(); size],
+            front: vec![None; size],
+        }
+    }
+    /// The width of the canvas in characters.
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+    /// The height of the canvas in characters.
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+    /// Clear the canvas to blank characters in the terminal's default colors.
+    pub fn clear(&mut self) {
+        for c in self.back.iter_mut() {
+            *c = Default::default();
+        }
+    }
+    /// Forget what is on the terminal, so the next `present` writes every character.
+    /// Useful if something else has written to the terminal.
+    pub fn invalidate(&mut self) {
+        for c in self.front.iter_mut() {
+            *c = None;
+        }
+    }
+    /// Write every character that has changed since the last call to the terminal, and flush it.
+    /// Returns the number of characters written.
+    pub fn present(&mut self) -> io::Result<u32> {
+        let mut cursor : Option<(u32,u32)> = None;
+        let mut colors : Option<(Option<Color>, Option<Color>)> = None;
+        let mut count = 0;
+        let mut i = 0;
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let c = self.back[i];
+                if self.front[i] != Some(c) {
+                    match cursor {
+                        Some((cx, cy)) if cy == y && cx == x => {},
+                        Some((cx, cy)) if cy == y && cx < x => write!(self.out, "\x1b[{}C", x - cx)?,
+                        _ => write!(self.out, "\x1b[{};{}H", y + 1, x + 1)?,
+                    }
+                    match colors {
+                        Some((fg, bg)) if fg == c.fg && bg == c.bg => {},
+                        _ => {
+                            write_color(&mut self.out, 3, c.fg)?;
+                            write_color(&mut self.out, 4, c.bg)?;
+                            colors = Some((c.fg, c.bg));
+                        }
+                    }
+                    write!(self.out, "{}", c.ch)?;
+                    self.front[i] = Some(c);
+                    // The cursor position is unreliable after writing to the last column.
+                    cursor = if x + 1 < self.width { Some((x + 1, y)) } else { None };
+                    count += 1;
+                }
+                i += 1;
+            }
+        }
+        if colors.is_some() {
+            write!(self.out, "\x1b[0m")?;
+        }
+        self.out.flush()?;
+        Ok(count)
+    }
+    /// The output the canvas writes to.
+    pub fn output(&mut self) -> &mut W {
+        &mut self.out
+    }
+}
+
+/// The terminal equivalent of a texture: each tile of a graphic rendered into terminal characters.
+pub struct TerminalTexture {
+    mode: TerminalMode,
+    width: u32,
+    cells: Vec<TerminalCell>,
+    /// The characters for each tile index, worked out from the tile set on first use
+    /// and again whenever its character map changes.
+    chars: Option<(TileSetVersion, Vec<Option<char>>)>,
+}
+
+impl TerminalTexture {
+    /// A terminal texture for a graphic of the given size in tiles.
+    pub fn new(mode: TerminalMode, width: u32, height: u32) -> TerminalTexture {
+        let (cw, ch) = mode.cell_size();
+        TerminalTexture {
+            mode,
+            width,
+            cells: vec![Default::default(); (width * cw * height * ch) as usize],
+            chars: None,
+        }
+    }
+}
+
+impl Graphic<()> {
+    /// A method to attach a terminal texture, converting the graphic from an unrenderable one to
+    /// one that can be drawn to a `TerminalCanvas`. Much like `textured`, the texture has not been
+    /// rendered yet, so typically you would want to call `update_texture` before drawing.
+    pub fn terminal_textured(&self, mode: TerminalMode) -> Graphic<TerminalTexture> {
+        self.with_backend(TerminalTexture::new(mode, self.width, self.height))
+    }
+}
+
+impl Graphic<TerminalTexture> {
+    /// The way this graphic's tiles are shown in the terminal.
+    pub fn terminal_mode(&self) -> TerminalMode {
+        self.texture.mode
+    }
+}
+
+impl Backend for TerminalTexture {
+    fn update_tile(&mut self, x: u32, y: u32, tile: Tile, tile_set: &TileSet) -> bool {
+        let (cw, ch) = self.mode.cell_size();
+        let stride = (self.width * cw) as usize;
+        let start = (x * cw) as usize + (y * ch) as usize * stride;
+        let stale = match self.chars {
+            Some((version, _)) => tile_set.char_map_changed_since(version),
+            None => true,
+        };
+        if stale {
+            self.chars = Some((tile_set.version(), tile_characters(tile_set)));
+        }
+        let chars = &self.chars.as_ref().unwrap().1;
+        render_tile(self.mode, tile, tile_set, chars, &mut self.cells[start..], stride);
+        true
+    }
+    fn invalidate(&mut self) {
+        self.chars = None;
+    }
+}
+
+impl <W: Write> Draw<TerminalCanvas<W>> for TerminalTexture {
+    /// Draw the graphic to the terminal canvas, with its top-left corner at the given position in characters.
+    /// Anything outside the canvas is clipped.
+    fn draw(graphic: &Graphic<TerminalTexture>, canvas: &mut TerminalCanvas<W>, position: (i32, i32)) {
+        let (cw, ch) = graphic.texture.mode.cell_size();
+        let (w, h) = ((graphic.width * cw) as i32, (graphic.height * ch) as i32);
+        for y in 0..h {
+            let cy = position.1 + y;
+            if cy < 0 || cy >= canvas.height as i32 { continue }
+            for x in 0..w {
+                let cx = position.0 + x;
+                if cx < 0 || cx >= canvas.width as i32 { continue }
+                canvas.back[(cx + cy * canvas.width as i32) as usize] = graphic.texture.cells[(x + y * w) as usize];
+            }
+        }
+    }
+}
+
+impl <T> Graphic<T> {
+    /// Write the whole graphic to the given output as lines of text with 24-bit ANSI colors,
+    /// suitable for printing to a terminal from a command line tool.
+    pub fn write_terminal<W: Write>(&self, tile_set: &TileSet, mode: TerminalMode, out: &mut W) -> io::Result<()> {
+        let (cw, ch) = mode.cell_size();
+        let stride = (self.width * cw) as usize;
+        let chars = tile_characters(tile_set);
+        let mut row = vec![TerminalCell::default(); stride * ch as usize];
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let t = self.tiles[(x + y * self.width) as usize];
+                render_tile(mode, t, tile_set, &chars, &mut row[(x * cw) as usize..], stride);
+            }
+            for line in row.chunks(stride) {
+                let mut colors = None;
+                for c in line {
+                    if colors != Some((c.fg, c.bg)) {
+                        write_color(out, 3, c.fg)?;
+                        write_color(out, 4, c.bg)?;
+                        colors = Some((c.fg, c.bg));
+                    }
+                    write!(out, "{}", c.ch)?;
+                }
+                writeln!(out, "\x1b[0m")?;
+            }
+        }
+        Ok(())
+    }
+}
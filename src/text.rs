@@ -0,0 +1,342 @@
+//! A plain text format for tile sets and graphics, for keeping them in version control where
+//! changes to them can be read and reviewed. It converts both ways with the binary formats of
+//! `TileSet::store` and `Graphic::save` without losing anything.
+//!
+//! A tile set starts with the line `tesserae tile set`, followed by each tile that isn't blank
+//! or has characters or properties: a line with `tile`, the tile index and the characters mapped
+//! to it (quoted, with `\xNN` escapes outside of printable ASCII), and then the tile's eight rows
+//! of pixels, with `#` for foreground and `.` for background. Any characters not listed map to tile 0.
+//! The tile's properties follow, if it has any: a line with `name` and the name, a line with `tag`
+//! and the tag for each tag, `solid` if it is solid, and a line with `property`, the key and the
+//! value for each other property, with strings quoted and escaped as in Rust.
+//!
+//! ```text
+//! tesserae tile set
+//! tile 2 'A'
+//! ..####..
+//! .##..##.
+//! .##..##.
+//! .######.
+//! .######.
+//! .##..##.
+//! .##..##.
+//! ........
+//! name "letter A"
+//! tag "letter"
+//! ```
+//!
+//! A graphic starts with a line with `tesserae graphic`, its width and its height, then a legend of its
+//! colors, each a line with `color`, the color's number and the color as `#rrggbb` (or `#rrggbbaa` if it
+//! isn't opaque), then a line for each row of tiles, each tile written as its index, foreground color
+//! number and background color number separated by colons.
+//!
+//! ```text
+//! tesserae graphic 3 1
+//! color 0 #ffffff
+//! color 1 #000000
+//! 2:0:1 3:0:1 0:1:1
+//! ```
+//!
+//! Blank lines and trailing spaces are ignored.
+
+use std::fs::File;
+use std::io;
+use std::io::{Read, Write};
+use std::path::Path;
+use std::collections::HashMap;
+
+use super::{Color, Graphic, Tile, TileSet};
+
+const TILE_SET_HEADER: &str = "tesserae tile set";
+const GRAPHIC_HEADER: &str = "tesserae graphic";
+
+fn invalid(line: usize, message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("line {}: {}", line, message))
+}
+
+/// The non-blank lines of the text, with their line numbers.
+fn lines(text: &str) -> impl Iterator<Item = (usize, &str)> {
+    text.lines().enumerate().map(|(n, l)| (n + 1, l.trim_end())).filter(|&(_, l)| !l.is_empty())
+}
+
+fn write_char<W: Write>(file: &mut W, code: u8) -> io::Result<()> {
+    match code {
+        b'\'' | b'\\' => write!(file, " '\\{}'", code as char),
+        0x20..=0x7E => write!(file, " '{}'", code as char),
+        _ => write!(file, " '\\x{:02x}'", code),
+    }
+}
+
+/// Parse a list of quoted characters, as written by `write_char`.
+fn parse_chars(s: &str) -> Option<Vec<u8>> {
+    let s = s.as_bytes();
+    let mut codes = Vec::new();
+    let mut i = 0;
+    loop {
+        while s.get(i) == Some(&b' ') {
+            i += 1;
+        }
+        if i == s.len() {
+            return Some(codes);
+        }
+        if s[i] != b'\'' {
+            return None;
+        }
+        let (code, len) = match *s.get(i + 1)? {
+            b'\\' if s.get(i + 2) == Some(&b'x') => {
+                let hex = std::str::from_utf8(s.get(i + 3..i + 5)?).ok()?;
+                (u8::from_str_radix(hex, 16).ok().filter(|_| hex.bytes().all(|b| b.is_ascii_hexdigit()))?, 4)
+            }
+            b'\\' => (*s.get(i + 2)?, 2),
+            c @ 0x20..=0x7E => (c, 1),
+            _ => return None,
+        };
+        if s.get(i + 1 + len) != Some(&b'\'') {
+            return None;
+        }
+        codes.push(code);
+        i += len + 2;
+    }
+}
+
+fn write_string<W: Write>(file: &mut W, s: &str) -> io::Result<()> {
+    write!(file, " {:?}", s)
+}
+
+/// Parse a list of quoted strings, as written by `write_string`.
+fn parse_strings(s: &str) -> Option<Vec<String>> {
+    let mut chars = s.chars().peekable();
+    let mut strings = Vec::new();
+    loop {
+        while chars.next_if_eq(&' ').is_some() {}
+        match chars.next() {
+            None => return Some(strings),
+            Some('"') => {}
+            Some(_) => return None,
+        }
+        let mut string = String::new();
+        loop {
+            match chars.next()? {
+                '"' => break,
+                '\\' => string.push(match chars.next()? {
+                    'n' => '\n',
+                    'r' => '\r',
+                    't' => '\t',
+                    '0' => '\0',
+                    c @ ('\\' | '"' | '\'') => c,
+                    'u' => {
+                        if chars.next()? != '{' {
+                            return None;
+                        }
+                        let hex: String = chars.by_ref().take_while(|&c| c != '}').collect();
+                        char::from_u32(u32::from_str_radix(&hex, 16).ok()?)?
+                    }
+                    _ => return None,
+                }),
+                c => string.push(c),
+            }
+        }
+        strings.push(string);
+    }
+}
+
+fn parse_tile(s: &str, colors: &HashMap<usize, Color>) -> Option<Tile> {
+    let mut parts = s.split(':').map(|p| p.parse::<usize>().ok());
+    let index = parts.next()??;
+    let fg = *colors.get(&parts.next()??)?;
+    let bg = *colors.get(&parts.next()??)?;
+    if parts.next().is_some() { None } else { Some(Tile { index, fg, bg }) }
+}
+
+impl TileSet {
+    /// Save the tile set in the text format to some instance of `Write` (such as a file).
+    pub fn save_text<W: Write>(&self, file: &mut W) -> io::Result<()> {
+        writeln!(file, "{}", TILE_SET_HEADER)?;
+        for (index, &data) in self.data.iter().enumerate() {
+            let codes: Vec<u8> = (0..=255u8).filter(|&c| index != 0 && self.char_map[c as usize] == index).collect();
+            let properties = self.tile_properties(index).filter(|p| !p.is_empty());
+            if data == 0 && codes.is_empty() && properties.is_none() {
+                continue;
+            }
+            writeln!(file)?;
+            write!(file, "tile {}", index)?;
+            for code in codes {
+                write_char(file, code)?;
+            }
+            writeln!(file)?;
+            for row in 0..8 {
+                let pixels: String = (0..8).map(|x| if data & (1 << (x + row * 8)) != 0 { '#' } else { '.' }).collect();
+                writeln!(file, "{}", pixels)?;
+            }
+            if let Some(p) = properties {
+                if !p.name.is_empty() {
+                    write!(file, "name")?;
+                    write_string(file, &p.name)?;
+                    writeln!(file)?;
+                }
+                for tag in &p.tags {
+                    write!(file, "tag")?;
+                    write_string(file, tag)?;
+                    writeln!(file)?;
+                }
+                if p.solid {
+                    writeln!(file, "solid")?;
+                }
+                for (key, value) in &p.values {
+                    write!(file, "property")?;
+                    write_string(file, key)?;
+                    write_string(file, value)?;
+                    writeln!(file)?;
+                }
+            }
+        }
+        Ok(())
+    }
+    /// Save the tile set in the text format to a file at the provided path.
+    pub fn store_text<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        self.save_text(&mut File::create(path)?)
+    }
+    /// Load a tile set in the text format from the file with the given path.
+    /// Sugar for `load_text_from` with `File::open`.
+    pub fn load_text_file<P: AsRef<Path>>(path: P) -> io::Result<TileSet> {
+        TileSet::load_text_from(File::open(path)?)
+    }
+    /// Load a tile set in the text format from a `Read` instance such as a file.
+    /// Tiles that aren't listed are blank.
+    pub fn load_text_from<R: Read>(mut input: R) -> io::Result<TileSet> {
+        let mut text = String::new();
+        input.read_to_string(&mut text)?;
+        let mut lines = lines(&text);
+        if lines.next().map(|(_, l)| l) != Some(TILE_SET_HEADER) {
+            return Err(invalid(1, "not a tesserae tile set"));
+        }
+        let mut ts = TileSet::blank();
+        ts.char_map = [0; 256];
+        let mut current = None;
+        while let Some((n, line)) = lines.next() {
+            let (keyword, rest) = line.split_once(' ').unwrap_or((line, ""));
+            if keyword != "tile" {
+                let index = current.ok_or_else(|| invalid(n, "expected a tile"))?;
+                let strings = parse_strings(rest).ok_or_else(|| invalid(n, "invalid string"))?;
+                let p = ts.tile_properties_mut(index);
+                match (keyword, &strings[..]) {
+                    ("name", [name]) => p.name = name.clone(),
+                    ("tag", [tag]) => { p.tags.insert(tag.clone()); }
+                    ("solid", []) => p.solid = true,
+                    ("property", [key, value]) => { p.values.insert(key.clone(), value.clone()); }
+                    _ => return Err(invalid(n, "expected a tile or property")),
+                }
+                continue;
+            }
+            let (index, chars) = rest.split_once(' ').unwrap_or((rest, ""));
+            let index = index.parse().ok().filter(|&i| i < ts.len()).ok_or_else(|| invalid(n, "invalid tile index"))?;
+            for code in parse_chars(chars).ok_or_else(|| invalid(n, "invalid characters"))? {
+                ts.char_map[code as usize] = index;
+            }
+            let mut data = 0;
+            for row in 0..8 {
+                let (n, pixels) = lines.next().ok_or_else(|| invalid(n, "tile has fewer than 8 rows"))?;
+                if pixels.len() != 8 {
+                    return Err(invalid(n, "tile row isn't 8 pixels wide"));
+                }
+                for (x, p) in pixels.bytes().enumerate() {
+                    match p {
+                        b'#' => data |= 1 << (x + row * 8),
+                        b'.' => {}
+                        _ => return Err(invalid(n, "pixels must be '#' or '.'")),
+                    }
+                }
+            }
+            ts[index] = data;
+            current = Some(index);
+        }
+        ts.properties.retain(|_, p| !p.is_empty());
+        Ok(ts)
+    }
+}
+
+impl<T> Graphic<T> {
+    /// Save the graphic in the text format to some instance of `Write` (such as a file).
+    /// Colors are numbered in the order they first appear.
+    pub fn save_text<W: Write>(&self, file: &mut W) -> io::Result<()> {
+        writeln!(file, "{} {} {}", GRAPHIC_HEADER, self.width, self.height)?;
+        let mut colors = Vec::new();
+        let mut numbers = HashMap::new();
+        for t in &self.tiles {
+            for &c in [t.fg, t.bg].iter() {
+                numbers.entry(c).or_insert_with(|| {
+                    colors.push(c);
+                    colors.len() - 1
+                });
+            }
+        }
+        for (i, c) in colors.iter().enumerate() {
+            writeln!(file, "color {} {}", i, c)?;
+        }
+        let cells: Vec<String> = self.tiles.iter().map(|t| format!("{}:{}:{}", t.index, numbers[&t.fg], numbers[&t.bg])).collect();
+        let width = cells.iter().map(|c| c.len()).max().unwrap_or(0);
+        for row in cells.chunks(self.width.max(1) as usize) {
+            let line: Vec<String> = row.iter().map(|c| format!("{:<1$}", c, width)).collect();
+            writeln!(file, "{}", line.join(" ").trim_end())?;
+        }
+        Ok(())
+    }
+    /// Save the graphic in the text format to a file at the provided path.
+    pub fn store_text<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        self.save_text(&mut File::create(path)?)
+    }
+}
+
+impl Graphic<()> {
+    /// Load a graphic in the text format from the file with the given path.
+    /// Sugar for `load_text_from` with `File::open`.
+    pub fn load_text_file<P: AsRef<Path>>(path: P) -> io::Result<Graphic<()>> {
+        Graphic::load_text_from(File::open(path)?)
+    }
+    /// Load a graphic in the text format from a `Read` instance such as a file.
+    pub fn load_text_from<R: Read>(mut input: R) -> io::Result<Graphic<()>> {
+        let mut text = String::new();
+        input.read_to_string(&mut text)?;
+        let mut lines = lines(&text).peekable();
+        let (n, header) = lines.next().ok_or_else(|| invalid(1, "not a tesserae graphic"))?;
+        let (width, height) = header.strip_prefix(GRAPHIC_HEADER).and_then(|s| {
+            let mut size = s.split_whitespace().map(|d| d.parse::<u32>().ok());
+            let (width, height) = (size.next()??, size.next()??);
+            if size.next().is_some() { None } else { Some((width, height)) }
+        }).ok_or_else(|| invalid(n, "not a tesserae graphic"))?;
+        let mut colors = HashMap::new();
+        while let Some(&(n, line)) = lines.peek() {
+            let rest = match line.strip_prefix("color ") {
+                Some(rest) => rest,
+                None => break,
+            };
+            let (number, color) = rest.split_once(' ').ok_or_else(|| invalid(n, "invalid color"))?;
+            let number = number.parse().map_err(|_| invalid(n, "invalid color number"))?;
+            colors.insert(number, Color::from_hex(color.trim_start()).ok_or_else(|| invalid(n, "invalid color"))?);
+            lines.next();
+        }
+        (width as usize).checked_mul(height as usize).ok_or_else(|| invalid(n, "graphic is too large"))?;
+        // The rows are read before the graphic is made, so a header claiming a huge size
+        // runs out of rows rather than making an enormous graphic first.
+        let mut tiles = Vec::new();
+        // A graphic with no width has no rows to write.
+        for _ in 0..if width == 0 { 0 } else { height } {
+            let (n, line) = lines.next().ok_or_else(|| invalid(n, "graphic has too few rows"))?;
+            let cells: Vec<&str> = line.split_whitespace().collect();
+            if cells.len() != width as usize {
+                return Err(invalid(n, &format!("row doesn't have {} tiles", width)));
+            }
+            for cell in cells {
+                tiles.push(parse_tile(cell, &colors).ok_or_else(|| invalid(n, &format!("invalid tile '{}'", cell)))?);
+            }
+        }
+        if let Some((n, _)) = lines.next() {
+            return Err(invalid(n, "graphic has too many rows"));
+        }
+        let mut g = Graphic::blank(width, height);
+        for (i, tile) in tiles.into_iter().enumerate() {
+            g.set_tile(i as u32 % width, i as u32 / width, tile);
+        }
+        Ok(g)
+    }
+}
@@ -0,0 +1,458 @@
+//! Import and export of files for the Tiled map editor (<https://www.mapeditor.org>), so that large
+//! maps can be drawn in Tiled and used with tesserae.
+//!
+//! ```ignore
+//! tile_set.store_tsx("tiles.tsx")?;                // also writes tiles.png
+//! level.store_tmx("level.tmx", "tiles.tsx")?;
+//! let level = Graphic::load_tmx_file("level.tmx")?;
+//! ```
+//!
+//! A tile set is exported as a TSX tile set with a PNG image sheet of its tiles, drawn in white on
+//! transparent so that Tiled can tint them, with one extra solid tile after them for backgrounds.
+//! Any `TileProperties` become Tiled tile properties. The character map isn't exported, as Tiled
+//! has no use for it.
+//!
+//! As Tiled has no colors for single cells, a graphic is exported as a TMX map with a tile layer for
+//! each background color, filled with the solid tile and tinted with the color, then one for each
+//! foreground color, with the tiles of that color tinted with it. The layers have a `tesserae`
+//! property of `background` or `foreground`. When importing, the layers, including any drawn in Tiled,
+//! are read in order: the solid tile gives a cell the layer's tint (white if it has none) as its
+//! background color, and any other tile is placed in the cell with the tint as its foreground color.
+//! Tile layers must be stored as CSV or uncompressed base64, and maps can't be infinite.
+
+use std::fs::File;
+use std::io;
+use std::io::{Read, Write};
+use std::path::Path;
+
+use super::{Color, Graphic, Tile, TileSet, TILESET_SIZE};
+
+/// The number of tiles in each row of the image sheet.
+const COLUMNS: usize = 32;
+/// The bits of a Tiled global tile ID that flip or rotate the tile.
+const FLIP_FLAGS: u32 = 0xF000_0000;
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+fn escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&apos;"),
+            '\n' => out.push_str("&#10;"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+fn unescape(s: &str) -> io::Result<String> {
+    let mut out = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(start) = rest.find('&') {
+        out.push_str(&rest[..start]);
+        let end = rest[start..].find(';').ok_or_else(|| invalid("unterminated entity in XML"))? + start;
+        let entity = &rest[start + 1..end];
+        let c = match entity {
+            "amp" => '&',
+            "lt" => '<',
+            "gt" => '>',
+            "quot" => '"',
+            "apos" => '\'',
+            _ => {
+                let code = match entity.strip_prefix("#x") {
+                    Some(hex) => u32::from_str_radix(hex, 16).ok(),
+                    None => entity.strip_prefix('#').and_then(|d| d.parse().ok()),
+                };
+                code.and_then(char::from_u32).ok_or_else(|| invalid("unknown entity in XML"))?
+            }
+        };
+        out.push(c);
+        rest = &rest[end + 1..];
+    }
+    out.push_str(rest);
+    Ok(out)
+}
+
+/// A piece of an XML document: an element's start (and whether it is also its end), an element's end, or text.
+enum Event {
+    Start(String, Vec<(String, String)>, bool),
+    End(String),
+    Text(String),
+}
+
+/// Split an XML document into events. This is only as much of XML as Tiled writes:
+/// declarations, comments and doctypes are skipped, and CDATA isn't supported.
+fn parse_xml(text: &str) -> io::Result<Vec<Event>> {
+    let mut events = Vec::new();
+    let mut rest = text;
+    while !rest.is_empty() {
+        let start = rest.find('<').unwrap_or(rest.len());
+        if !rest[..start].trim().is_empty() {
+            events.push(Event::Text(unescape(&rest[..start])?));
+        }
+        rest = &rest[start..];
+        if rest.is_empty() {
+            break;
+        }
+        if let Some(comment) = rest.strip_prefix("<!--") {
+            let end = comment.find("-->").ok_or_else(|| invalid("unterminated comment in XML"))?;
+            rest = &comment[end + 3..];
+            continue;
+        }
+        let end = rest.find('>').ok_or_else(|| invalid("unterminated tag in XML"))?;
+        let tag = &rest[1..end];
+        rest = &rest[end + 1..];
+        if tag.starts_with('?') || tag.starts_with('!') {
+            continue;
+        }
+        if let Some(name) = tag.strip_prefix('/') {
+            events.push(Event::End(name.trim().to_string()));
+            continue;
+        }
+        let (tag, empty) = match tag.strip_suffix('/') {
+            Some(tag) => (tag, true),
+            None => (tag, false),
+        };
+        let name_end = tag.find(char::is_whitespace).unwrap_or(tag.len());
+        let mut attributes = Vec::new();
+        let mut attrs = tag[name_end..].trim_start();
+        while !attrs.is_empty() {
+            let eq = attrs.find('=').ok_or_else(|| invalid("attribute without a value in XML"))?;
+            let key = attrs[..eq].trim().to_string();
+            let value = attrs[eq + 1..].trim_start();
+            let quote = value.chars().next().filter(|&q| q == '"' || q == '\'').ok_or_else(|| invalid("unquoted attribute in XML"))?;
+            let close = value[1..].find(quote).ok_or_else(|| invalid("unterminated attribute in XML"))? + 1;
+            attributes.push((key, unescape(&value[1..close])?));
+            attrs = value[close + 1..].trim_start();
+        }
+        events.push(Event::Start(tag[..name_end].to_string(), attributes, empty));
+    }
+    Ok(events)
+}
+
+fn attribute<'a>(attributes: &'a [(String, String)], key: &str) -> Option<&'a str> {
+    attributes.iter().find(|(k, _)| k == key).map(|(_, v)| v.as_str())
+}
+
+fn number_attribute(attributes: &[(String, String)], key: &str) -> io::Result<u32> {
+    attribute(attributes, key).and_then(|v| v.parse().ok()).ok_or_else(|| invalid("missing or invalid number in TMX file"))
+}
+
+/// A Tiled color, `#aarrggbb` or `#rrggbb`.
+fn tiled_color(c: Color) -> String {
+    if c.a == 255 {
+        format!("#{:02x}{:02x}{:02x}", c.r, c.g, c.b)
+    } else {
+        format!("#{:02x}{:02x}{:02x}{:02x}", c.a, c.r, c.g, c.b)
+    }
+}
+
+fn parse_tiled_color(s: &str) -> Option<Color> {
+    let hex = s.strip_prefix('#').unwrap_or(s);
+    if !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return None;
+    }
+    let n = u32::from_str_radix(hex, 16).ok()?;
+    let [a, r, g, b] = n.to_be_bytes();
+    match hex.len() {
+        6 => Some(Color::RGBA(r, g, b, 255)),
+        8 => Some(Color::RGBA(r, g, b, a)),
+        _ => None,
+    }
+}
+
+fn base64_decode(s: &str) -> Option<Vec<u8>> {
+    let mut out = Vec::new();
+    let (mut bits, mut count) = (0u32, 0);
+    for b in s.bytes().filter(|b| !b.is_ascii_whitespace()) {
+        let v = match b {
+            b'A'..=b'Z' => b - b'A',
+            b'a'..=b'z' => b - b'a' + 26,
+            b'0'..=b'9' => b - b'0' + 52,
+            b'+' => 62,
+            b'/' => 63,
+            b'=' => break,
+            _ => return None,
+        };
+        bits = (bits << 6) | v as u32;
+        count += 6;
+        if count >= 8 {
+            count -= 8;
+            out.push((bits >> count) as u8);
+        }
+    }
+    Some(out)
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &b in data {
+        crc ^= b as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 { (crc >> 1) ^ 0xEDB8_8320 } else { crc >> 1 };
+        }
+    }
+    !crc
+}
+
+fn write_chunk<W: Write>(file: &mut W, kind: &[u8; 4], data: &[u8]) -> io::Result<()> {
+    file.write_all(&(data.len() as u32).to_be_bytes())?;
+    let mut chunk = kind.to_vec();
+    chunk.extend_from_slice(data);
+    file.write_all(&chunk)?;
+    file.write_all(&crc32(&chunk).to_be_bytes())
+}
+
+/// Write an RGBA image as a PNG file, without compression, which keeps the encoder small.
+fn write_png<W: Write>(file: &mut W, width: usize, height: usize, pixels: &[u8]) -> io::Result<()> {
+    file.write_all(b"\x89PNG\r\n\x1a\n")?;
+    let mut header = Vec::new();
+    header.extend_from_slice(&(width as u32).to_be_bytes());
+    header.extend_from_slice(&(height as u32).to_be_bytes());
+    header.extend_from_slice(&[8, 6, 0, 0, 0]);
+    write_chunk(file, b"IHDR", &header)?;
+    let mut raw = Vec::new();
+    for row in pixels.chunks(width * 4) {
+        raw.push(0);
+        raw.extend_from_slice(row);
+    }
+    // A zlib stream of stored deflate blocks.
+    let mut data = vec![0x78, 0x01];
+    let blocks = raw.chunks(0xFFFF).collect::<Vec<_>>();
+    for (i, block) in blocks.iter().enumerate() {
+        data.push((i + 1 == blocks.len()) as u8);
+        data.extend_from_slice(&(block.len() as u16).to_le_bytes());
+        data.extend_from_slice(&(!(block.len() as u16)).to_le_bytes());
+        data.extend_from_slice(block);
+    }
+    let (mut a, mut b) = (1u32, 0u32);
+    for &byte in &raw {
+        a = (a + byte as u32) % 65521;
+        b = (b + a) % 65521;
+    }
+    data.extend_from_slice(&((b << 16) | a).to_be_bytes());
+    write_chunk(file, b"IDAT", &data)?;
+    write_chunk(file, b"IEND", &[])
+}
+
+impl TileSet {
+    /// Save the image sheet of the tile set as a PNG to some instance of `Write` (such as a file).
+    /// The tiles are in rows of 32, in white on transparent, followed by a solid white tile.
+    pub fn save_tile_sheet<W: Write>(&self, file: &mut W) -> io::Result<()> {
+        let count = self.len() + 1;
+        let (width, height) = (COLUMNS * 8, count.div_ceil(COLUMNS) * 8);
+        let mut pixels = vec![0; width * height * 4];
+        for index in 0..count {
+            let data = if index == self.len() { !0 } else { self[index] };
+            let (left, top) = (index % COLUMNS * 8, index / COLUMNS * 8);
+            for bit in 0..64 {
+                if data & (1 << bit) != 0 {
+                    let p = ((top + bit / 8) * width + left + bit % 8) * 4;
+                    pixels[p..p + 4].copy_from_slice(&[255, 255, 255, 255]);
+                }
+            }
+        }
+        write_png(file, width, height, &pixels)
+    }
+    /// Save the tile set as a Tiled TSX tile set to some instance of `Write` (such as a file),
+    /// using the image sheet at the given path (relative to the TSX file), as saved by `save_tile_sheet`.
+    pub fn save_tsx<W: Write>(&self, file: &mut W, image: &str) -> io::Result<()> {
+        let count = self.len() + 1;
+        let rows = count.div_ceil(COLUMNS);
+        let name = Path::new(image).file_stem().map_or("tiles".into(), |s| s.to_string_lossy());
+        writeln!(file, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+        writeln!(file, r#"<tileset version="1.10" name="{}" tilewidth="8" tileheight="8" tilecount="{}" columns="{}">"#, escape(&name), count, COLUMNS)?;
+        writeln!(file, r#" <image source="{}" width="{}" height="{}"/>"#, escape(image), COLUMNS * 8, rows * 8)?;
+        for index in 0..self.len() {
+            let p = match self.tile_properties(index).filter(|p| !p.is_empty()) {
+                Some(p) => p,
+                None => continue,
+            };
+            writeln!(file, r#" <tile id="{}">"#, index)?;
+            writeln!(file, "  <properties>")?;
+            if !p.name.is_empty() {
+                writeln!(file, r#"   <property name="name" value="{}"/>"#, escape(&p.name))?;
+            }
+            if p.solid {
+                writeln!(file, r#"   <property name="solid" type="bool" value="true"/>"#)?;
+            }
+            if !p.tags.is_empty() {
+                let tags: Vec<&str> = p.tags.iter().map(|t| t.as_str()).collect();
+                writeln!(file, r#"   <property name="tags" value="{}"/>"#, escape(&tags.join(",")))?;
+            }
+            for (key, value) in &p.values {
+                writeln!(file, r#"   <property name="{}" value="{}"/>"#, escape(key), escape(value))?;
+            }
+            writeln!(file, "  </properties>")?;
+            writeln!(file, " </tile>")?;
+        }
+        writeln!(file, "</tileset>")
+    }
+    /// Save the tile set as a Tiled TSX tile set at the provided path, with its image sheet
+    /// alongside it, with the same name but the extension `png`.
+    pub fn store_tsx<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let path = path.as_ref();
+        let image = path.with_extension("png");
+        self.save_tile_sheet(&mut File::create(&image)?)?;
+        let source = image.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+        self.save_tsx(&mut File::create(path)?, &source)
+    }
+}
+
+impl<T> Graphic<T> {
+    /// Save the graphic as a Tiled TMX map to some instance of `Write` (such as a file), using the TSX
+    /// tile set at the given path (relative to the TMX file), as saved by `TileSet::save_tsx`.
+    pub fn save_tmx<W: Write>(&self, file: &mut W, tile_set: &str) -> io::Result<()> {
+        let mut layers: Vec<(&str, Color)> = Vec::new();
+        for &(kind, foreground) in [("background", false), ("foreground", true)].iter() {
+            for t in &self.tiles {
+                let c = if foreground { t.fg } else { t.bg };
+                if !layers.contains(&(kind, c)) {
+                    layers.push((kind, c));
+                }
+            }
+        }
+        writeln!(file, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+        writeln!(file, r#"<map version="1.10" orientation="orthogonal" renderorder="right-down" width="{}" height="{}" tilewidth="8" tileheight="8" infinite="0" nextlayerid="{}" nextobjectid="1">"#,
+            self.width, self.height, layers.len() + 1)?;
+        writeln!(file, r#" <tileset firstgid="1" source="{}"/>"#, escape(tile_set))?;
+        for (id, &(kind, color)) in layers.iter().enumerate() {
+            writeln!(file, r#" <layer id="{}" name="{} {}" width="{}" height="{}" tintcolor="{}">"#,
+                id + 1, kind, tiled_color(color), self.width, self.height, tiled_color(color))?;
+            writeln!(file, "  <properties>")?;
+            writeln!(file, r#"   <property name="tesserae" value="{}"/>"#, kind)?;
+            writeln!(file, "  </properties>")?;
+            writeln!(file, r#"  <data encoding="csv">"#)?;
+            let gids: Vec<String> = self.tiles.iter().map(|t| {
+                let gid = match kind {
+                    "background" if t.bg == color => TILESET_SIZE + 1,
+                    "foreground" if t.fg == color => t.index + 1,
+                    _ => 0,
+                };
+                gid.to_string()
+            }).collect();
+            let rows: Vec<String> = gids.chunks(self.width.max(1) as usize).map(|r| r.join(",")).collect();
+            writeln!(file, "{}", rows.join(",\n"))?;
+            writeln!(file, "  </data>")?;
+            writeln!(file, " </layer>")?;
+        }
+        writeln!(file, "</map>")
+    }
+    /// Save the graphic as a Tiled TMX map at the provided path.
+    pub fn store_tmx<P: AsRef<Path>>(&self, path: P, tile_set: &str) -> io::Result<()> {
+        self.save_tmx(&mut File::create(path)?, tile_set)
+    }
+}
+
+impl Graphic<()> {
+    /// Load the tile layers of a Tiled TMX map from the file with the given path as a graphic.
+    /// Sugar for `load_tmx_from` with `File::open`.
+    pub fn load_tmx_file<P: AsRef<Path>>(path: P) -> io::Result<Graphic<()>> {
+        Graphic::load_tmx_from(File::open(path)?)
+    }
+    /// Load the tile layers of a Tiled TMX map from a `Read` instance (such as a file) as a graphic.
+    /// Tile indices are relative to the map's first tile set, and cells of other tile sets' tiles
+    /// are left out. Cells without any tiles are blank. Maps without any tile layers can't be loaded.
+    pub fn load_tmx_from<R: Read>(mut input: R) -> io::Result<Graphic<()>> {
+        let mut text = String::new();
+        input.read_to_string(&mut text)?;
+        // The graphic is only made once a layer's data has been read, so that the size
+        // a map claims to be can't make an enormous graphic before any tiles are seen.
+        let mut size = None;
+        let mut graphic: Option<Graphic<()>> = None;
+        let mut first_gid = None;
+        // The tint of the current layer, and the encoding of its data.
+        let mut layer = None;
+        let mut encoding = None;
+        let mut elements = Vec::new();
+        for event in parse_xml(&text)? {
+            match event {
+                Event::Start(name, attributes, empty) => {
+                    match (name.as_str(), elements.last().map(|e: &String| e.as_str())) {
+                        ("map", None) => {
+                            if attribute(&attributes, "infinite") == Some("1") {
+                                return Err(invalid("infinite TMX maps aren't supported"));
+                            }
+                            size = Some((number_attribute(&attributes, "width")?, number_attribute(&attributes, "height")?));
+                        }
+                        ("tileset", Some("map")) if first_gid.is_none() => {
+                            first_gid = Some(number_attribute(&attributes, "firstgid")? as usize);
+                        }
+                        ("layer", Some("map")) | ("layer", Some("group")) => {
+                            layer = Some(match attribute(&attributes, "tintcolor") {
+                                Some(c) => parse_tiled_color(c).ok_or_else(|| invalid("invalid tint color in TMX file"))?,
+                                None => Color::RGBA(255, 255, 255, 255),
+                            });
+                        }
+                        ("data", Some("layer")) => {
+                            if attribute(&attributes, "compression").is_some() {
+                                return Err(invalid("compressed TMX layers aren't supported"));
+                            }
+                            encoding = Some(attribute(&attributes, "encoding").unwrap_or("xml").to_string());
+                        }
+                        ("chunk", Some("data")) => return Err(invalid("infinite TMX maps aren't supported")),
+                        ("tile", Some("data")) => return Err(invalid("TMX layers stored as XML aren't supported")),
+                        _ => {}
+                    }
+                    if !empty {
+                        elements.push(name);
+                    }
+                }
+                Event::End(name) => {
+                    if elements.pop().as_ref() != Some(&name) {
+                        return Err(invalid("mismatched tags in TMX file"));
+                    }
+                    if name == "layer" {
+                        layer = None;
+                    }
+                }
+                Event::Text(data) => {
+                    if elements.last().map(|e| e.as_str()) != Some("data") {
+                        continue;
+                    }
+                    let (width, height) = size.ok_or_else(|| invalid("not a TMX map"))?;
+                    let tint = layer.ok_or_else(|| invalid("tile data outside of a layer in TMX file"))?;
+                    let gids: Vec<u32> = match encoding.as_deref() {
+                        Some("csv") => data.split(',').map(|g| g.trim().parse().map_err(|_| invalid("invalid tile in TMX file"))).collect::<io::Result<_>>()?,
+                        Some("base64") => {
+                            let bytes = base64_decode(&data).ok_or_else(|| invalid("invalid base64 in TMX file"))?;
+                            if bytes.len() % 4 != 0 {
+                                return Err(invalid("TMX layer data isn't a whole number of tiles"));
+                            }
+                            bytes.chunks_exact(4).map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]])).collect()
+                        }
+                        _ => return Err(invalid("unsupported TMX layer encoding")),
+                    };
+                    if (width as usize).checked_mul(height as usize) != Some(gids.len()) {
+                        return Err(invalid("TMX layer is a different size from the map"));
+                    }
+                    let g = graphic.get_or_insert_with(|| Graphic::blank(width, height));
+                    let first = first_gid.unwrap_or(1);
+                    for (t, &gid) in g.tiles.iter_mut().zip(gids.iter()) {
+                        let gid = (gid & !FLIP_FLAGS) as usize;
+                        if gid < first {
+                            continue;
+                        }
+                        match gid - first {
+                            TILESET_SIZE => t.bg = tint,
+                            index if index < TILESET_SIZE => *t = Tile { index, fg: tint, bg: t.bg },
+                            _ => {}
+                        }
+                    }
+                }
+            }
+        }
+        match (size, graphic) {
+            (Some(_), Some(g)) => Ok(g),
+            (Some(_), None) => Err(invalid("TMX map has no tile layers")),
+            (None, _) => Err(invalid("not a TMX map")),
+        }
+    }
+}
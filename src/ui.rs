@@ -0,0 +1,827 @@
+//! User interface widgets drawn with tiles, such as buttons, checkboxes, text inputs, lists,
+//! scroll views and menus, for the menus and dialogs of games and tools.
+//!
+//! Widgets are placed in a `Ui`, which draws them into a graphic and passes events on to them:
+//! keys and text go to the widget with the focus, which moves between widgets with tab and
+//! shift-tab, and mouse events go to the widget under the mouse, which takes the focus when clicked.
+//!
+//! ```ignore
+//! let mut ui = Ui::new();
+//! let name = ui.add(TextInput::new(16), 1, 1);
+//! let ok = ui.add(Button::new("OK"), 1, 3);
+//! for event in event_pump.poll_iter() {
+//!     if let Some(e) = ui::Event::from_sdl(&event, (0, 0), 8) {
+//!         if ui.handle(&e) == Some((ok, Response::Activated)) {
+//!             println!("hello, {}", ui.widget::<TextInput>(name).unwrap().text());
+//!         }
+//!     }
+//! }
+//! ui.draw(&mut screen, &tile_set);
+//! screen.update_texture(&tile_set);
+//! ```
+//!
+//! Events are independent of SDL, so a UI can be driven by any source of input; with the `sdl`
+//! feature, `Event::from_sdl` converts SDL events. Widgets draw text with the tile set's character
+//! map, and their borders and scroll bars with ASCII characters, so they work with any tile set.
+//! New kinds of widget can be made by implementing the `Widget` trait.
+
+use std::any::Any;
+
+use super::{Color, Graphic, Tile, TileSet};
+
+/// A key that widgets respond to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Key {
+    Up,
+    Down,
+    Left,
+    Right,
+    Home,
+    End,
+    PageUp,
+    PageDown,
+    Enter,
+    Escape,
+    Space,
+    Tab,
+    /// Tab with shift held.
+    BackTab,
+    Backspace,
+    Delete,
+}
+
+/// Input for a UI. Positions are in tiles, relative to the top-left of the UI
+/// (or of the widget, once passed on to one).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Event {
+    Key(Key),
+    /// Text typed, separately from the keys pressed to type it.
+    Text(String),
+    /// The (primary) mouse button was pressed.
+    MouseDown { x: i32, y: i32 },
+    /// The (primary) mouse button was released.
+    MouseUp { x: i32, y: i32 },
+    MouseMove { x: i32, y: i32 },
+    /// The mouse wheel was turned, by the given number of lines, with positive numbers scrolling down.
+    Wheel(i32),
+}
+
+impl Event {
+    /// The position of a mouse event.
+    pub fn position(&self) -> Option<(i32, i32)> {
+        match *self {
+            Event::MouseDown { x, y } | Event::MouseUp { x, y } | Event::MouseMove { x, y } => Some((x, y)),
+            _ => None,
+        }
+    }
+    /// The event with its position (if it has one) made relative to the given point.
+    pub fn relative_to(&self, x: i32, y: i32) -> Event {
+        match *self {
+            Event::MouseDown { x: ex, y: ey } => Event::MouseDown { x: ex - x, y: ey - y },
+            Event::MouseUp { x: ex, y: ey } => Event::MouseUp { x: ex - x, y: ey - y },
+            Event::MouseMove { x: ex, y: ey } => Event::MouseMove { x: ex - x, y: ey - y },
+            ref e => e.clone(),
+        }
+    }
+}
+
+/// What a widget did with an event.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Response {
+    /// The widget had no use for the event.
+    Ignored,
+    /// The widget used the event, but its value hasn't changed.
+    Handled,
+    /// The widget's value changed, such as a checkbox being toggled or a list selection moving.
+    Changed,
+    /// The widget was activated, such as a button being pressed or a list item chosen.
+    Activated,
+    /// The widget was dismissed, such as a menu with escape.
+    Cancelled,
+}
+
+/// The colors widgets are drawn in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Theme {
+    pub fg: Color,
+    pub bg: Color,
+    /// The colors of the widget with the focus.
+    pub focus_fg: Color,
+    pub focus_bg: Color,
+    /// The colors of selected items, such as in a list without the focus.
+    pub selected_fg: Color,
+    pub selected_bg: Color,
+}
+
+impl Default for Theme {
+    /// Light gray on black, with the focus in black on cyan.
+    fn default() -> Theme {
+        Theme {
+            fg: Color::RGBA(0xaa, 0xaa, 0xaa, 255),
+            bg: Color::RGBA(0, 0, 0, 255),
+            focus_fg: Color::RGBA(0, 0, 0, 255),
+            focus_bg: Color::RGBA(0x55, 0xff, 0xff, 255),
+            selected_fg: Color::RGBA(0xff, 0xff, 0xff, 255),
+            selected_bg: Color::RGBA(0x55, 0x55, 0x55, 255),
+        }
+    }
+}
+
+impl Theme {
+    /// The colors of the focused widget if `focused` is set, or else the usual colors.
+    pub fn colors(&self, focused: bool) -> (Color, Color) {
+        if focused { (self.focus_fg, self.focus_bg) } else { (self.fg, self.bg) }
+    }
+    /// The colors of a selected item, in a widget with the focus if `focused` is set.
+    pub fn selected_colors(&self, focused: bool) -> (Color, Color) {
+        if focused { (self.focus_fg, self.focus_bg) } else { (self.selected_fg, self.selected_bg) }
+    }
+}
+
+/// Lets `Ui` give back widgets as their own types. Implemented for every type, so widgets needn't implement it.
+pub trait AsAny {
+    fn as_any(&self) -> &dyn Any;
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+impl<T: Any> AsAny for T {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+/// Something that can be placed in a `Ui`, which draws itself into a graphic and responds to events.
+pub trait Widget: AsAny {
+    /// The width and height of the widget, in tiles.
+    fn size(&self) -> (u32, u32);
+    /// Draw the widget into a graphic of its size, in the theme's colors.
+    fn draw(&self, graphic: &mut Graphic<()>, tile_set: &TileSet, theme: &Theme, focused: bool);
+    /// Respond to an event, with positions relative to the widget's top-left. The widget only gets
+    /// key and text events when it has the focus, and mouse events when the mouse is over it.
+    fn handle(&mut self, event: &Event) -> Response;
+    /// Whether the widget can take the focus, which by default it can.
+    fn focusable(&self) -> bool {
+        true
+    }
+}
+
+/// The tile for a character, with characters outside the character map shown as `?`.
+fn char_tile(tile_set: &TileSet, c: char) -> usize {
+    tile_set[if (c as u32) < 256 { c } else { '?' }]
+}
+
+/// Draw text in a row of the given width, cut off or padded with spaces to fill it.
+fn draw_text(graphic: &mut Graphic<()>, tile_set: &TileSet, text: &str, x: u32, y: u32, width: u32, (fg, bg): (Color, Color)) {
+    let mut chars = text.chars();
+    for i in 0..width {
+        let index = char_tile(tile_set, chars.next().unwrap_or(' '));
+        graphic.set_tile(x + i, y, Tile { index, fg, bg });
+    }
+}
+
+/// Draw a vertical scroll bar in a column of the given height, for a view as many lines high
+/// starting at line `offset` of a total of `total`.
+fn draw_scroll_bar(graphic: &mut Graphic<()>, tile_set: &TileSet, x: u32, height: u32, offset: usize, total: usize, colors: (Color, Color)) {
+    let height = height as usize;
+    let thumb = (height * height / total.max(1)).clamp(1, height);
+    let start = (offset * height / total.max(1)).min(height - thumb);
+    for y in 0..height {
+        let c = if y >= start && y < start + thumb { '#' } else { '|' };
+        graphic.set_tile(x, y as u32, Tile { index: char_tile(tile_set, c), fg: colors.0, bg: colors.1 });
+    }
+}
+
+fn text_width(text: &str) -> u32 {
+    text.chars().count() as u32
+}
+
+/// Whether a mouse event is inside a widget of the given size.
+fn inside(event: &Event, (width, height): (u32, u32)) -> bool {
+    event.position().is_some_and(|(x, y)| x >= 0 && y >= 0 && (x as u32) < width && (y as u32) < height)
+}
+
+/// Text that can't be focused or interacted with.
+pub struct Label {
+    pub text: String,
+}
+
+impl Label {
+    pub fn new<S: Into<String>>(text: S) -> Label {
+        Label { text: text.into() }
+    }
+}
+
+impl Widget for Label {
+    fn size(&self) -> (u32, u32) {
+        (text_width(&self.text), 1)
+    }
+    fn draw(&self, graphic: &mut Graphic<()>, tile_set: &TileSet, theme: &Theme, _focused: bool) {
+        draw_text(graphic, tile_set, &self.text, 0, 0, text_width(&self.text), theme.colors(false));
+    }
+    fn handle(&mut self, _event: &Event) -> Response {
+        Response::Ignored
+    }
+    fn focusable(&self) -> bool {
+        false
+    }
+}
+
+/// A button, shown as its label in brackets, which is activated by enter, space or a click.
+pub struct Button {
+    pub label: String,
+}
+
+impl Button {
+    pub fn new<S: Into<String>>(label: S) -> Button {
+        Button { label: label.into() }
+    }
+}
+
+impl Widget for Button {
+    fn size(&self) -> (u32, u32) {
+        (text_width(&self.label) + 2, 1)
+    }
+    fn draw(&self, graphic: &mut Graphic<()>, tile_set: &TileSet, theme: &Theme, focused: bool) {
+        draw_text(graphic, tile_set, &format!("[{}]", self.label), 0, 0, self.size().0, theme.colors(focused));
+    }
+    fn handle(&mut self, event: &Event) -> Response {
+        match event {
+            Event::Key(Key::Enter) | Event::Key(Key::Space) => Response::Activated,
+            Event::MouseDown { .. } if inside(event, self.size()) => Response::Activated,
+            _ => Response::Ignored,
+        }
+    }
+}
+
+/// A box that can be checked or not, with a label, toggled by enter, space or a click.
+pub struct Checkbox {
+    pub label: String,
+    pub checked: bool,
+}
+
+impl Checkbox {
+    pub fn new<S: Into<String>>(label: S, checked: bool) -> Checkbox {
+        Checkbox { label: label.into(), checked }
+    }
+}
+
+impl Widget for Checkbox {
+    fn size(&self) -> (u32, u32) {
+        (text_width(&self.label) + 4, 1)
+    }
+    fn draw(&self, graphic: &mut Graphic<()>, tile_set: &TileSet, theme: &Theme, focused: bool) {
+        let text = format!("[{}] {}", if self.checked { 'x' } else { ' ' }, self.label);
+        draw_text(graphic, tile_set, &text, 0, 0, self.size().0, theme.colors(focused));
+    }
+    fn handle(&mut self, event: &Event) -> Response {
+        match event {
+            Event::Key(Key::Enter) | Event::Key(Key::Space) => {}
+            Event::MouseDown { .. } if inside(event, self.size()) => {}
+            _ => return Response::Ignored,
+        }
+        self.checked = !self.checked;
+        Response::Changed
+    }
+}
+
+/// A single line of editable text, scrolled to keep the cursor in view.
+/// Enter activates it.
+pub struct TextInput {
+    text: Vec<char>,
+    cursor: usize,
+    scroll: usize,
+    width: u32,
+    /// The most characters the text can have, if there is a limit.
+    pub max_len: Option<usize>,
+}
+
+impl TextInput {
+    /// An empty text input the given number of tiles wide.
+    pub fn new(width: u32) -> TextInput {
+        TextInput { text: Vec::new(), cursor: 0, scroll: 0, width: width.max(1), max_len: None }
+    }
+    /// The text entered.
+    pub fn text(&self) -> String {
+        self.text.iter().collect()
+    }
+    /// Replace the text, putting the cursor at the end.
+    pub fn set_text(&mut self, text: &str) {
+        self.text = text.chars().collect();
+        self.cursor = self.text.len();
+        self.scroll_to_cursor();
+    }
+    /// The position of the cursor, in characters.
+    pub fn cursor(&self) -> usize {
+        self.cursor
+    }
+    fn scroll_to_cursor(&mut self) {
+        let width = self.width as usize;
+        if self.cursor < self.scroll {
+            self.scroll = self.cursor;
+        } else if self.cursor >= self.scroll + width {
+            self.scroll = self.cursor + 1 - width;
+        }
+    }
+}
+
+impl Widget for TextInput {
+    fn size(&self) -> (u32, u32) {
+        (self.width, 1)
+    }
+    fn draw(&self, graphic: &mut Graphic<()>, tile_set: &TileSet, theme: &Theme, focused: bool) {
+        let visible: String = self.text.iter().skip(self.scroll).collect();
+        let colors = theme.colors(focused);
+        draw_text(graphic, tile_set, &visible, 0, 0, self.width, colors);
+        if focused {
+            // The cursor is shown by swapping the colors of the character under it.
+            let x = (self.cursor - self.scroll) as u32;
+            graphic.color_tile(x, 0, colors.1, colors.0);
+        }
+    }
+    fn handle(&mut self, event: &Event) -> Response {
+        let response = match event {
+            Event::Text(text) => {
+                let room = self.max_len.map_or(usize::MAX, |m| m.saturating_sub(self.text.len()));
+                let typed: Vec<char> = text.chars().filter(|c| !c.is_control()).take(room).collect();
+                if typed.is_empty() {
+                    return Response::Handled;
+                }
+                let n = typed.len();
+                self.text.splice(self.cursor..self.cursor, typed);
+                self.cursor += n;
+                Response::Changed
+            }
+            Event::Key(Key::Backspace) if self.cursor > 0 => {
+                self.cursor -= 1;
+                self.text.remove(self.cursor);
+                Response::Changed
+            }
+            Event::Key(Key::Delete) if self.cursor < self.text.len() => {
+                self.text.remove(self.cursor);
+                Response::Changed
+            }
+            Event::Key(Key::Backspace) | Event::Key(Key::Delete) => Response::Handled,
+            Event::Key(Key::Left) => {
+                self.cursor = self.cursor.saturating_sub(1);
+                Response::Handled
+            }
+            Event::Key(Key::Right) => {
+                self.cursor = (self.cursor + 1).min(self.text.len());
+                Response::Handled
+            }
+            Event::Key(Key::Home) => {
+                self.cursor = 0;
+                Response::Handled
+            }
+            Event::Key(Key::End) => {
+                self.cursor = self.text.len();
+                Response::Handled
+            }
+            Event::Key(Key::Enter) => Response::Activated,
+            &Event::MouseDown { x, .. } if inside(event, self.size()) => {
+                self.cursor = (self.scroll + x as usize).min(self.text.len());
+                Response::Handled
+            }
+            _ => Response::Ignored,
+        };
+        self.scroll_to_cursor();
+        response
+    }
+}
+
+/// A list of items, one of which may be selected, scrolled to keep the selection in view, with a scroll bar
+/// if the items don't all fit. Enter, or clicking on the selected item, activates it.
+pub struct List {
+    items: Vec<String>,
+    selected: Option<usize>,
+    scroll: usize,
+    width: u32,
+    height: u32,
+}
+
+impl List {
+    /// A list of the given size in tiles, with the first item (if any) selected.
+    pub fn new(items: Vec<String>, width: u32, height: u32) -> List {
+        let selected = if items.is_empty() { None } else { Some(0) };
+        List { items, selected, scroll: 0, width: width.max(1), height: height.max(1) }
+    }
+    pub fn items(&self) -> &[String] {
+        &self.items
+    }
+    /// Replace the items, keeping the selection if it is still in range.
+    pub fn set_items(&mut self, items: Vec<String>) {
+        self.items = items;
+        self.select(self.selected.unwrap_or(0));
+    }
+    /// The index of the selected item, if there is one.
+    pub fn selected(&self) -> Option<usize> {
+        self.selected
+    }
+    /// The selected item, if there is one.
+    pub fn selected_item(&self) -> Option<&str> {
+        self.selected.map(|i| self.items[i].as_str())
+    }
+    /// Select the item with the given index, or the last item if it is out of range.
+    pub fn select(&mut self, index: usize) {
+        self.selected = if self.items.is_empty() { None } else { Some(index.min(self.items.len() - 1)) };
+        if let Some(i) = self.selected {
+            let height = self.height as usize;
+            if i < self.scroll {
+                self.scroll = i;
+            } else if i >= self.scroll + height {
+                self.scroll = i + 1 - height;
+            }
+        }
+        self.scroll = self.scroll.min(self.items.len().saturating_sub(self.height as usize));
+    }
+    /// Select the item at the given offset from the selected one, returning whether the selection changed.
+    fn move_selection(&mut self, delta: i64) -> Response {
+        let old = self.selected;
+        let current = old.unwrap_or(0) as i64;
+        self.select((current + delta).max(0) as usize);
+        if self.selected == old { Response::Handled } else { Response::Changed }
+    }
+}
+
+impl Widget for List {
+    fn size(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+    fn draw(&self, graphic: &mut Graphic<()>, tile_set: &TileSet, theme: &Theme, focused: bool) {
+        let scrolls = self.items.len() > self.height as usize;
+        let width = if scrolls { self.width - 1 } else { self.width };
+        for y in 0..self.height {
+            let i = self.scroll + y as usize;
+            let colors = if Some(i) == self.selected { theme.selected_colors(focused) } else { theme.colors(false) };
+            draw_text(graphic, tile_set, self.items.get(i).map_or("", |s| s.as_str()), 0, y, width, colors);
+        }
+        if scrolls {
+            draw_scroll_bar(graphic, tile_set, width, self.height, self.scroll, self.items.len(), theme.colors(focused));
+        }
+    }
+    fn handle(&mut self, event: &Event) -> Response {
+        let page = self.height as i64;
+        match *event {
+            Event::Key(Key::Up) => self.move_selection(-1),
+            Event::Key(Key::Down) => self.move_selection(1),
+            Event::Key(Key::PageUp) => self.move_selection(-page),
+            Event::Key(Key::PageDown) => self.move_selection(page),
+            Event::Key(Key::Home) => self.move_selection(i64::MIN / 2),
+            Event::Key(Key::End) => self.move_selection(i64::MAX / 2),
+            Event::Key(Key::Enter) if self.selected.is_some() => Response::Activated,
+            Event::MouseDown { y, .. } if inside(event, self.size()) => {
+                let i = self.scroll + y as usize;
+                if i >= self.items.len() {
+                    Response::Handled
+                } else if Some(i) == self.selected {
+                    Response::Activated
+                } else {
+                    self.select(i);
+                    Response::Changed
+                }
+            }
+            Event::Wheel(lines) => {
+                let max = self.items.len().saturating_sub(self.height as usize) as i64;
+                self.scroll = (self.scroll as i64 + lines as i64).clamp(0, max) as usize;
+                Response::Handled
+            }
+            _ => Response::Ignored,
+        }
+    }
+}
+
+/// A view of part of a larger graphic, such as help text, which can be scrolled with the arrow keys,
+/// page up and down, and the mouse wheel. A scroll bar is shown if the graphic is taller than the view.
+pub struct ScrollView {
+    content: Graphic<()>,
+    width: u32,
+    height: u32,
+    scroll_x: u32,
+    scroll_y: u32,
+}
+
+impl ScrollView {
+    /// A view of the given size in tiles, showing the top-left of the content.
+    pub fn new(content: Graphic<()>, width: u32, height: u32) -> ScrollView {
+        ScrollView { content, width: width.max(1), height: height.max(1), scroll_x: 0, scroll_y: 0 }
+    }
+    pub fn content(&self) -> &Graphic<()> {
+        &self.content
+    }
+    /// The content, for changing it.
+    pub fn content_mut(&mut self) -> &mut Graphic<()> {
+        &mut self.content
+    }
+    /// The position in the content shown at the top-left of the view.
+    pub fn scroll(&self) -> (u32, u32) {
+        (self.scroll_x, self.scroll_y)
+    }
+    /// Show the content from the given position, as far as it can be scrolled.
+    pub fn scroll_to(&mut self, x: u32, y: u32) {
+        self.scroll_x = x.min(self.content.width.saturating_sub(self.view_width()));
+        self.scroll_y = y.min(self.content.height.saturating_sub(self.height));
+    }
+    fn scroll_by(&mut self, dx: i64, dy: i64) -> Response {
+        let old = self.scroll();
+        let x = (self.scroll_x as i64 + dx).max(0) as u32;
+        let y = (self.scroll_y as i64 + dy).max(0) as u32;
+        self.scroll_to(x, y);
+        if self.scroll() == old { Response::Ignored } else { Response::Handled }
+    }
+    fn scrolls(&self) -> bool {
+        self.content.height > self.height
+    }
+    /// The width of the view without its scroll bar.
+    fn view_width(&self) -> u32 {
+        if self.scrolls() { self.width - 1 } else { self.width }
+    }
+}
+
+impl Widget for ScrollView {
+    fn size(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+    fn draw(&self, graphic: &mut Graphic<()>, tile_set: &TileSet, theme: &Theme, focused: bool) {
+        graphic.draw_rect(0, 0, self.width, self.height, Tile { index: char_tile(tile_set, ' '), fg: theme.fg, bg: theme.bg });
+        graphic.copy_tiles_from(&self.content, self.scroll_x, self.scroll_y, self.view_width(), self.height, 0, 0);
+        if self.scrolls() {
+            let (offset, total) = (self.scroll_y as usize, self.content.height as usize);
+            draw_scroll_bar(graphic, tile_set, self.width - 1, self.height, offset, total, theme.colors(focused));
+        }
+    }
+    fn handle(&mut self, event: &Event) -> Response {
+        let page = self.height as i64;
+        match *event {
+            Event::Key(Key::Up) => self.scroll_by(0, -1),
+            Event::Key(Key::Down) => self.scroll_by(0, 1),
+            Event::Key(Key::Left) => self.scroll_by(-1, 0),
+            Event::Key(Key::Right) => self.scroll_by(1, 0),
+            Event::Key(Key::PageUp) => self.scroll_by(0, -page),
+            Event::Key(Key::PageDown) => self.scroll_by(0, page),
+            Event::Key(Key::Home) => self.scroll_by(0, i64::MIN / 2),
+            Event::Key(Key::End) => self.scroll_by(0, i64::MAX / 2),
+            Event::Wheel(lines) => {
+                self.scroll_by(0, lines as i64);
+                Response::Handled
+            }
+            Event::MouseDown { .. } if inside(event, self.size()) => Response::Handled,
+            _ => Response::Ignored,
+        }
+    }
+}
+
+/// A menu of items in a box, one of which is highlighted, moved with the arrow keys, the mouse, or by typing
+/// the first letter of an item. Enter, space or a click activates the highlighted item, and escape cancels the menu.
+pub struct Menu {
+    items: Vec<String>,
+    selected: usize,
+}
+
+impl Menu {
+    /// A menu with the first item highlighted.
+    pub fn new(items: Vec<String>) -> Menu {
+        Menu { items, selected: 0 }
+    }
+    pub fn items(&self) -> &[String] {
+        &self.items
+    }
+    /// The index of the highlighted item.
+    pub fn selected(&self) -> usize {
+        self.selected
+    }
+    /// Highlight the item with the given index, or the last item if it is out of range.
+    pub fn select(&mut self, index: usize) {
+        self.selected = index.min(self.items.len().saturating_sub(1));
+    }
+    /// The item under a position relative to the menu, if any.
+    fn item_at(&self, x: i32, y: i32) -> Option<usize> {
+        let (width, height) = self.size();
+        if x < 1 || y < 1 || x as u32 + 1 >= width || y as u32 + 1 >= height {
+            None
+        } else {
+            Some(y as usize - 1)
+        }
+    }
+    fn highlight(&mut self, index: usize) -> Response {
+        if index == self.selected {
+            Response::Handled
+        } else {
+            self.selected = index;
+            Response::Changed
+        }
+    }
+}
+
+impl Widget for Menu {
+    fn size(&self) -> (u32, u32) {
+        (self.items.iter().map(|i| text_width(i)).max().unwrap_or(0) + 2, self.items.len() as u32 + 2)
+    }
+    fn draw(&self, graphic: &mut Graphic<()>, tile_set: &TileSet, theme: &Theme, focused: bool) {
+        let (width, height) = self.size();
+        let colors = theme.colors(false);
+        let border = format!("+{}+", "-".repeat(width as usize - 2));
+        draw_text(graphic, tile_set, &border, 0, 0, width, colors);
+        draw_text(graphic, tile_set, &border, 0, height - 1, width, colors);
+        for (i, item) in self.items.iter().enumerate() {
+            let y = i as u32 + 1;
+            draw_text(graphic, tile_set, "|", 0, y, 1, colors);
+            let item_colors = if i == self.selected { theme.selected_colors(focused) } else { colors };
+            draw_text(graphic, tile_set, item, 1, y, width - 2, item_colors);
+            draw_text(graphic, tile_set, "|", width - 1, y, 1, colors);
+        }
+    }
+    fn handle(&mut self, event: &Event) -> Response {
+        if self.items.is_empty() {
+            return if *event == Event::Key(Key::Escape) { Response::Cancelled } else { Response::Ignored };
+        }
+        let last = self.items.len() - 1;
+        match event {
+            Event::Key(Key::Up) => self.highlight(if self.selected == 0 { last } else { self.selected - 1 }),
+            Event::Key(Key::Down) => self.highlight(if self.selected == last { 0 } else { self.selected + 1 }),
+            Event::Key(Key::Home) | Event::Key(Key::PageUp) => self.highlight(0),
+            Event::Key(Key::End) | Event::Key(Key::PageDown) => self.highlight(last),
+            Event::Key(Key::Enter) | Event::Key(Key::Space) => Response::Activated,
+            Event::Key(Key::Escape) => Response::Cancelled,
+            Event::Text(text) => {
+                let typed = match text.chars().next() {
+                    Some(c) => c.to_lowercase().collect::<String>(),
+                    None => return Response::Handled,
+                };
+                // The next item starting with the letter, after the highlighted one.
+                let next = (1..=self.items.len()).map(|d| (self.selected + d) % self.items.len())
+                    .find(|&i| self.items[i].to_lowercase().starts_with(&typed));
+                match next {
+                    Some(i) => self.highlight(i),
+                    None => Response::Handled,
+                }
+            }
+            &Event::MouseMove { x, y } => match self.item_at(x, y) {
+                Some(i) => self.highlight(i),
+                None => Response::Ignored,
+            },
+            &Event::MouseDown { x, y } => match self.item_at(x, y) {
+                Some(i) => {
+                    self.selected = i;
+                    Response::Activated
+                }
+                None => Response::Handled,
+            },
+            _ => Response::Ignored,
+        }
+    }
+}
+
+/// Identifies a widget in a `Ui`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct WidgetId(usize);
+
+struct Placed {
+    widget: Box<dyn Widget>,
+    x: u32,
+    y: u32,
+    visible: bool,
+}
+
+/// A set of widgets, each at a position in tiles, which draws them and passes events on to them.
+/// Widgets added later are drawn over earlier ones.
+pub struct Ui {
+    widgets: Vec<Placed>,
+    focus: Option<usize>,
+    /// Where the mouse was last seen, for sending mouse wheel events to the widget under it.
+    mouse: Option<(i32, i32)>,
+    pub theme: Theme,
+}
+
+impl Default for Ui {
+    fn default() -> Ui {
+        Ui::new()
+    }
+}
+
+impl Ui {
+    /// A UI without any widgets, in the default theme.
+    pub fn new() -> Ui {
+        Ui { widgets: Vec::new(), focus: None, mouse: None, theme: Theme::default() }
+    }
+    /// Add a widget with its top-left corner at the given position. The first focusable widget added takes the focus.
+    pub fn add<W: Widget + 'static>(&mut self, widget: W, x: u32, y: u32) -> WidgetId {
+        self.widgets.push(Placed { widget: Box::new(widget), x, y, visible: true });
+        let id = self.widgets.len() - 1;
+        if self.focus.is_none() && self.can_focus(id) {
+            self.focus = Some(id);
+        }
+        WidgetId(id)
+    }
+    /// The widget with the given id, if it is of the given type.
+    pub fn widget<W: Widget + 'static>(&self, id: WidgetId) -> Option<&W> {
+        // Dereferenced, so that it's the widget rather than its box that is downcast.
+        self.widgets.get(id.0).and_then(|p| (*p.widget).as_any().downcast_ref())
+    }
+    /// The widget with the given id, if it is of the given type, for changing it.
+    pub fn widget_mut<W: Widget + 'static>(&mut self, id: WidgetId) -> Option<&mut W> {
+        self.widgets.get_mut(id.0).and_then(|p| (*p.widget).as_any_mut().downcast_mut())
+    }
+    /// The position of a widget's top-left corner.
+    pub fn position(&self, id: WidgetId) -> (u32, u32) {
+        let p = &self.widgets[id.0];
+        (p.x, p.y)
+    }
+    /// Move a widget's top-left corner to the given position.
+    pub fn move_widget(&mut self, id: WidgetId, x: u32, y: u32) {
+        let p = &mut self.widgets[id.0];
+        p.x = x;
+        p.y = y;
+    }
+    /// Whether a widget is shown.
+    pub fn is_visible(&self, id: WidgetId) -> bool {
+        self.widgets[id.0].visible
+    }
+    /// Show or hide a widget, such as a menu that pops up. Hidden widgets aren't drawn and get no events,
+    /// and the focus moves on from a widget that is hidden.
+    pub fn set_visible(&mut self, id: WidgetId, visible: bool) {
+        self.widgets[id.0].visible = visible;
+        if !visible && self.focus == Some(id.0) {
+            self.focus_next();
+        }
+    }
+    fn can_focus(&self, i: usize) -> bool {
+        self.widgets[i].visible && self.widgets[i].widget.focusable()
+    }
+    /// The widget with the focus, if any.
+    pub fn focus(&self) -> Option<WidgetId> {
+        self.focus.map(WidgetId)
+    }
+    /// Give the focus to a widget, if it can take it.
+    pub fn set_focus(&mut self, id: WidgetId) {
+        if self.can_focus(id.0) {
+            self.focus = Some(id.0);
+        }
+    }
+    /// Move the focus to the next widget that can take it, in the order they were added.
+    pub fn focus_next(&mut self) {
+        self.cycle_focus(true)
+    }
+    /// Move the focus to the previous widget that can take it, in the order they were added.
+    pub fn focus_previous(&mut self) {
+        self.cycle_focus(false)
+    }
+    fn cycle_focus(&mut self, forwards: bool) {
+        let n = self.widgets.len();
+        if n == 0 {
+            return;
+        }
+        let start = self.focus.unwrap_or(if forwards { n - 1 } else { 0 });
+        self.focus = (1..=n).map(|d| if forwards { (start + d) % n } else { (start + n - d) % n }).find(|&i| self.can_focus(i));
+    }
+    /// The topmost visible widget at the given position, if any.
+    pub fn widget_at(&self, x: i32, y: i32) -> Option<WidgetId> {
+        self.widgets.iter().enumerate().rev().find(|(_, p)| {
+            let (width, height) = p.widget.size();
+            p.visible && x >= p.x as i32 && y >= p.y as i32 && x < (p.x + width) as i32 && y < (p.y + height) as i32
+        }).map(|(i, _)| WidgetId(i))
+    }
+    /// Pass an event on to the widget it is for, returning that widget and its response,
+    /// unless no widget had any use for it. Tab and shift-tab move the focus.
+    pub fn handle(&mut self, event: &Event) -> Option<(WidgetId, Response)> {
+        let target = match *event {
+            Event::Key(Key::Tab) | Event::Key(Key::BackTab) if !self.widgets.is_empty() => {
+                self.cycle_focus(*event == Event::Key(Key::Tab));
+                return None;
+            }
+            Event::Key(_) | Event::Text(_) => self.focus.map(WidgetId),
+            Event::MouseDown { x, y } => {
+                let target = self.widget_at(x, y);
+                if let Some(id) = target {
+                    self.set_focus(id);
+                }
+                target
+            }
+            Event::MouseUp { x, y } => self.widget_at(x, y),
+            Event::MouseMove { x, y } => {
+                self.mouse = Some((x, y));
+                self.widget_at(x, y)
+            }
+            Event::Wheel(_) => self.mouse.and_then(|(x, y)| self.widget_at(x, y)).or(self.focus.map(WidgetId)),
+        }?;
+        let p = &mut self.widgets[target.0];
+        match p.widget.handle(&event.relative_to(p.x as i32, p.y as i32)) {
+            Response::Ignored => None,
+            response => Some((target, response)),
+        }
+    }
+    /// Draw the visible widgets into a graphic, such as the screen of a game.
+    pub fn draw<T>(&self, graphic: &mut Graphic<T>, tile_set: &TileSet) {
+        for (i, p) in self.widgets.iter().enumerate() {
+            if !p.visible {
+                continue;
+            }
+            let (width, height) = p.widget.size();
+            let mut g = Graphic::blank(width, height);
+            p.widget.draw(&mut g, tile_set, &self.theme, self.focus == Some(i));
+            graphic.copy_all_tiles_from(&g, p.x, p.y);
+        }
+    }
+}
@@ -0,0 +1,65 @@
+//! Keeping track of changes to tile sets, so that graphics and backends can redraw only what a change affects.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use super::TileSet;
+
+static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+
+/// A new identifier for a tile set, different from every other tile set's.
+pub(crate) fn next_id() -> u64 {
+    NEXT_ID.fetch_add(1, Ordering::Relaxed)
+}
+
+/// Identifies a tile set and how many times it had been changed, at some point in time.
+///
+/// Comparing a version saved earlier with the tile set's current one tells whether it has changed
+/// since, and `TileSet::tiles_changed_since` tells which tiles.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct TileSetVersion {
+    id: u64,
+    revision: u64,
+}
+
+impl TileSetVersion {
+    /// Whether the versions are of the same tile set.
+    pub fn same_tile_set(self, other: TileSetVersion) -> bool {
+        self.id == other.id
+    }
+}
+
+impl TileSet {
+    pub(crate) fn tile_changed(&mut self, index: usize) {
+        if index < self.data.len() {
+            self.revision += 1;
+            self.tile_revisions.resize(self.data.len(), 0);
+            self.tile_revisions[index] = self.revision;
+        }
+    }
+    pub(crate) fn char_map_changed(&mut self) {
+        self.revision += 1;
+        self.char_map_revision = self.revision;
+    }
+    /// The current version of the tile set, which changes whenever a tile or the character map is changed.
+    pub fn version(&self) -> TileSetVersion {
+        TileSetVersion { id: self.id, revision: self.revision }
+    }
+    /// The version of the tile set in which the tile with the given index last changed.
+    /// A tile's version is the same for as long as the tile stays the same.
+    pub fn tile_version(&self, index: usize) -> TileSetVersion {
+        TileSetVersion { id: self.id, revision: self.tile_revisions.get(index).copied().unwrap_or(0) }
+    }
+    /// The indices of the tiles that have changed since the given version of this tile set, or `None`
+    /// if the version is of a different tile set, in which case any of the tiles may be different.
+    pub fn tiles_changed_since(&self, version: TileSetVersion) -> Option<Vec<usize>> {
+        if version.id != self.id {
+            return None;
+        }
+        Some((0..self.tile_revisions.len()).filter(|&i| self.tile_revisions[i] > version.revision).collect())
+    }
+    /// Whether the character map has changed since the given version of this tile set.
+    /// Always true for a version of a different tile set.
+    pub fn char_map_changed_since(&self, version: TileSetVersion) -> bool {
+        version.id != self.id || self.char_map_revision > version.revision
+    }
+}
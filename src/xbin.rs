@@ -0,0 +1,275 @@
+//! Loading and saving XBin files (`.xb`), a format for text mode art that bundles
+//! an image of characters and attributes with the font and 16-color palette it was drawn with.
+//!
+//! These map almost directly onto tesserae: the font becomes a `TileSet`, the palette
+//! becomes the first 16 colors of a `Swatch`, and the image becomes a `Graphic`.
+//! Fonts of 512 characters (where the image may use only 8 foreground colors)
+//! fill the whole tile set.
+
+use std::fs::File;
+use std::io;
+use std::io::{Read, Write};
+use std::path::Path;
+use std::collections::HashMap;
+
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+
+use super::ansi::ANSI_PALETTE;
+use super::{Color, Graphic, Swatch, Tile, TileSet};
+
+const FLAG_PALETTE: u8 = 0x01;
+const FLAG_FONT: u8 = 0x02;
+const FLAG_COMPRESS: u8 = 0x04;
+const FLAG_NON_BLINK: u8 = 0x08;
+const FLAG_512_CHARS: u8 = 0x10;
+/// The most bytes of image data set aside before any of it has been read.
+const MAX_RESERVE: usize = 1 << 16;
+
+/// Text mode attributes number the colors differently to ANSI escape codes,
+/// with red and blue swapped.
+fn attribute_color(i: usize) -> Color {
+    ANSI_PALETTE[(i & 0x8) | ((i & 0x1) << 2) | (i & 0x2) | ((i & 0x4) >> 2)]
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+/// The contents of an XBin file.
+#[derive(Clone)]
+pub struct XBin {
+    /// The font. Files without a font use `TileSet::cga_ascii`.
+    pub tile_set: TileSet,
+    /// The palette, in the first 16 colors. Files without a palette use
+    /// the standard VGA text mode colors.
+    pub swatch: Swatch,
+    pub graphic: Graphic<()>,
+}
+
+impl XBin {
+    /// Bundle a graphic and tile set, with a palette made of the (up to) 16 colors used most in the graphic.
+    pub fn from_graphic<T>(graphic: &Graphic<T>, tile_set: &TileSet) -> XBin {
+        let mut counts : HashMap<Color, usize> = HashMap::new();
+        for t in &graphic.tiles {
+            *counts.entry(t.fg).or_insert(0) += 1;
+            *counts.entry(t.bg).or_insert(0) += 1;
+        }
+        let mut colors : Vec<(Color, usize)> = counts.into_iter().collect();
+        colors.sort_by_key(|&(c, n)| (std::cmp::Reverse(n), c.r, c.g, c.b, c.a));
+        let colors : Vec<Color> = colors.into_iter().take(16).map(|(c, _)| c).collect();
+        let mut g = Graphic::blank(graphic.width, graphic.height);
+        g.copy_all_tiles_from(graphic, 0, 0);
+        XBin {
+            tile_set: tile_set.clone(),
+            swatch: Swatch::from_colors(&colors),
+            graphic: g,
+        }
+    }
+    /// Load an XBin file from the given path. Sugar for `load_from` with `File::open`.
+    pub fn load_file<P: AsRef<Path>>(path: P) -> io::Result<XBin> {
+        XBin::load_from(File::open(path)?)
+    }
+    /// Load an XBin file from any instance of the `Read` trait.
+    ///
+    /// Fonts whose characters are not 8 pixels high are scaled to 8 pixels by skipping
+    /// or repeating rows, so the usual 16 pixel VGA fonts lose every other row.
+    pub fn load_from<R: Read>(mut input: R) -> io::Result<XBin> {
+        let mut id = [0; 5];
+        input.read_exact(&mut id)?;
+        if &id != b"XBIN\x1a" {
+            return Err(invalid("not an XBin file"));
+        }
+        let width = input.read_u16::<LittleEndian>()? as u32;
+        let height = input.read_u16::<LittleEndian>()? as u32;
+        let font_height = input.read_u8()? as usize;
+        let flags = input.read_u8()?;
+        let mut colors : Vec<Color> = (0..16).map(attribute_color).collect();
+        if flags & FLAG_PALETTE != 0 {
+            let mut p = [0; 48];
+            input.read_exact(&mut p)?;
+            // Palette entries are 6 bit VGA DAC values.
+            let scale = |v: u8| ((v & 0x3F) << 2) | ((v & 0x3F) >> 4);
+            for (i, c) in p.chunks(3).enumerate() {
+                colors[i] = Color::RGB(scale(c[0]), scale(c[1]), scale(c[2]));
+            }
+        }
+        let tile_set = if flags & FLAG_FONT != 0 {
+            if font_height == 0 || font_height > 32 {
+                return Err(invalid("invalid XBin font height"));
+            }
+            let chars = if flags & FLAG_512_CHARS != 0 { 512 } else { 256 };
+            let mut font = vec![0; chars * font_height];
+            input.read_exact(&mut font)?;
+            let mut ts = TileSet::blank();
+            for (i, glyph) in font.chunks(font_height).enumerate() {
+                let mut data = 0;
+                for row in 0..8 {
+                    let b = glyph[row * font_height / 8].reverse_bits();
+                    data |= (b as u64) << (row * 8);
+                }
+                ts[i] = data;
+            }
+            ts.char_map = TileSet::cga_ascii().char_map;
+            ts
+        } else {
+            TileSet::cga_ascii()
+        };
+        let size = (width * height) as usize;
+        // The cells are read before the graphic is made, and only a little room is reserved for them up front,
+        // so a file claiming to be huge runs out of data rather than having memory set aside for it first.
+        let mut cells = Vec::with_capacity((size * 2).min(MAX_RESERVE));
+        if flags & FLAG_COMPRESS != 0 {
+            while cells.len() < size * 2 {
+                let run = input.read_u8()?;
+                let count = (run & 0x3F) as usize + 1;
+                match run >> 6 {
+                    0 => for _ in 0..count {
+                        cells.push(input.read_u8()?);
+                        cells.push(input.read_u8()?);
+                    },
+                    1 => {
+                        let c = input.read_u8()?;
+                        for _ in 0..count {
+                            cells.push(c);
+                            cells.push(input.read_u8()?);
+                        }
+                    }
+                    2 => {
+                        let a = input.read_u8()?;
+                        for _ in 0..count {
+                            cells.push(input.read_u8()?);
+                            cells.push(a);
+                        }
+                    }
+                    _ => {
+                        let c = input.read_u8()?;
+                        let a = input.read_u8()?;
+                        for _ in 0..count {
+                            cells.push(c);
+                            cells.push(a);
+                        }
+                    }
+                }
+            }
+            cells.truncate(size * 2);
+        } else {
+            input.take(size as u64 * 2).read_to_end(&mut cells)?;
+            if cells.len() < size * 2 {
+                return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "XBin image is truncated"));
+            }
+        }
+        let mut graphic = Graphic::blank(width, height);
+        for (i, cell) in cells.chunks(2).enumerate() {
+            let (c, a) = (cell[0] as usize, cell[1] as usize);
+            let (index, fg) = if flags & FLAG_512_CHARS != 0 {
+                (c | ((a & 0x08) << 5), a & 0x07)
+            } else {
+                (c, a & 0x0F)
+            };
+            let bg = if flags & FLAG_NON_BLINK != 0 { a >> 4 } else { (a >> 4) & 0x07 };
+            graphic.tiles[i] = Tile { index, fg: colors[fg], bg: colors[bg] };
+        }
+        Ok(XBin { tile_set, swatch: Swatch::from_colors(&colors), graphic })
+    }
+    /// Save as an XBin file, including the font and palette, to some instance of `Write` (such as a file).
+    ///
+    /// Each color in the graphic is saved as the nearest of the first 16 colors in the swatch.
+    /// If the graphic uses any tile indices of 256 or more, the whole 512 tile set is saved,
+    /// which limits foreground colors to the first 8 colors in the swatch.
+    /// The image is compressed if `compress` is set.
+    pub fn save<W: Write>(&self, file: &mut W, compress: bool) -> io::Result<()> {
+        let g = &self.graphic;
+        let wide = g.tiles.iter().any(|t| t.index >= 256);
+        let mut flags = FLAG_PALETTE | FLAG_FONT | FLAG_NON_BLINK;
+        if wide { flags |= FLAG_512_CHARS }
+        if compress { flags |= FLAG_COMPRESS }
+        file.write_all(b"XBIN\x1a")?;
+        file.write_u16::<LittleEndian>(g.width as u16)?;
+        file.write_u16::<LittleEndian>(g.height as u16)?;
+        file.write_u8(8)?;
+        file.write_u8(flags)?;
+        for i in 0..16 {
+            let c = self.swatch[i];
+            file.write_all(&[c.r >> 2, c.g >> 2, c.b >> 2])?;
+        }
+        for i in 0..(if wide { 512 } else { 256 }) {
+            let data = if i < self.tile_set.len() { self.tile_set[i] } else { 0 };
+            for row in 0..8 {
+                file.write_u8(((data >> (row * 8)) as u8).reverse_bits())?;
+            }
+        }
+        let cells : Vec<(u8, u8)> = g.tiles.iter().map(|t| {
+            let bg = self.swatch.nearest(t.bg, 16) as u8;
+            if wide {
+                let fg = self.swatch.nearest(t.fg, 8) as u8;
+                (t.index as u8, fg | ((t.index >> 5) as u8 & 0x08) | (bg << 4))
+            } else {
+                (t.index as u8, self.swatch.nearest(t.fg, 16) as u8 | (bg << 4))
+            }
+        }).collect();
+        if compress {
+            for row in cells.chunks(g.width.max(1) as usize) {
+                write_compressed_row(file, row)?;
+            }
+        } else {
+            for (c, a) in cells {
+                file.write_all(&[c, a])?;
+            }
+        }
+        Ok(())
+    }
+    /// Save as an XBin file at the provided path, compressing the image.
+    pub fn store<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        self.save(&mut File::create(path)?, true)
+    }
+}
+
+/// The number of cells (up to the 64 that fit in one run) starting at `i` with the same key.
+fn run_length<K: PartialEq, F: Fn(&(u8, u8)) -> K>(row: &[(u8, u8)], i: usize, key: F) -> usize {
+    row[i..].iter().take(64).take_while(|c| key(c) == key(&row[i])).count()
+}
+
+/// Write a row of characters and attributes using XBin run length encoding.
+/// Runs never cross from one row to the next.
+fn write_compressed_row<W: Write>(file: &mut W, row: &[(u8, u8)]) -> io::Result<()> {
+    let mut i = 0;
+    while i < row.len() {
+        let both = run_length(row, i, |c| *c);
+        let chars = run_length(row, i, |c| c.0);
+        let attrs = run_length(row, i, |c| c.1);
+        if both >= 2 {
+            file.write_all(&[0xC0 | (both - 1) as u8, row[i].0, row[i].1])?;
+            i += both;
+        } else if chars >= 3 && chars >= attrs {
+            file.write_all(&[0x40 | (chars - 1) as u8, row[i].0])?;
+            for c in &row[i..i + chars] {
+                file.write_u8(c.1)?;
+            }
+            i += chars;
+        } else if attrs >= 3 {
+            file.write_all(&[0x80 | (attrs - 1) as u8, row[i].1])?;
+            for c in &row[i..i + attrs] {
+                file.write_u8(c.0)?;
+            }
+            i += attrs;
+        } else {
+            // Gather uncompressed cells until a run worth compressing begins.
+            let mut n = 1;
+            while i + n < row.len() && n < 64 {
+                let j = i + n;
+                if row.get(j + 1) == Some(&row[j])
+                    || run_length(row, j, |c| c.0) >= 3
+                    || run_length(row, j, |c| c.1) >= 3 {
+                    break;
+                }
+                n += 1;
+            }
+            file.write_u8((n - 1) as u8)?;
+            for c in &row[i..i + n] {
+                file.write_all(&[c.0, c.1])?;
+            }
+            i += n;
+        }
+    }
+    Ok(())
+}
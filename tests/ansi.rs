@@ -0,0 +1,26 @@
+use tesserae::ansi::{AnsiColors, Sauce, ANSI_PALETTE};
+use tesserae::{Graphic, Tile, TileSet};
+
+fn assert_same(a: &Graphic<()>, b: &Graphic<()>) {
+    assert_eq!((a.width(), a.height()), (b.width(), b.height()));
+    for y in 0..a.height() {
+        for x in 0..a.width() {
+            assert_eq!(a.get_tile(x, y), b.get_tile(x, y), "tile at ({}, {})", x, y);
+        }
+    }
+}
+
+#[test]
+fn empty_line_after_full_line_survives_round_trip() {
+    let tile_set = TileSet::cga_ascii();
+    let tile = |c| Tile { index: tile_set[c], fg: ANSI_PALETTE[7], bg: ANSI_PALETTE[0] };
+    let mut g = Graphic::solid(3, 3, tile(' '));
+    for x in 0..3 {
+        g.set_tile(x, 0, tile('A'));
+    }
+    g.set_tile(0, 2, tile('B'));
+    let mut data = Vec::new();
+    g.save_ansi(&mut data, &tile_set, AnsiColors::TrueColor, &Sauce::default()).unwrap();
+    let loaded = Graphic::load_ansi_from(&data[..], &tile_set).unwrap();
+    assert_same(&loaded, &g);
+}
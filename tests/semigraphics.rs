@@ -0,0 +1,41 @@
+use tesserae::semigraphics::{BlockGlyphs, BlockResolution};
+use tesserae::{Color, Graphic, TileSet};
+
+const BLACK: Color = Color::RGBA(0, 0, 0, 255);
+const RED: Color = Color::RGBA(255, 0, 0, 255);
+const GREEN: Color = Color::RGBA(0, 255, 0, 255);
+
+fn half_red_cell() -> (BlockGlyphs, Graphic<()>) {
+    let tile_set = TileSet::cga_ascii();
+    let blocks = BlockGlyphs::find(&tile_set, BlockResolution::Quadrants).unwrap();
+    let g = Graphic::solid(1, 1, blocks.tile(0b0011, RED, BLACK));
+    (blocks, g)
+}
+
+fn pixels(g: &Graphic<()>, blocks: &BlockGlyphs) -> Vec<Option<Color>> {
+    vec![g.pixel(blocks, 0, 0), g.pixel(blocks, 1, 0), g.pixel(blocks, 0, 1), g.pixel(blocks, 1, 1)]
+}
+
+#[test]
+fn plot_keeps_background_of_half_block() {
+    let (blocks, mut g) = half_red_cell();
+    assert_eq!(pixels(&g, &blocks), vec![Some(RED), Some(RED), Some(BLACK), Some(BLACK)]);
+    g.plot(&blocks, 0, 0, GREEN);
+    assert_eq!(pixels(&g, &blocks), vec![Some(GREEN), Some(GREEN), Some(BLACK), Some(BLACK)]);
+}
+
+#[test]
+fn unplot_keeps_background_of_half_block() {
+    let (blocks, mut g) = half_red_cell();
+    g.unplot(&blocks, 0, 1);
+    assert_eq!(pixels(&g, &blocks), vec![Some(RED), Some(RED), Some(BLACK), Some(BLACK)]);
+}
+
+#[test]
+fn plot_keeps_background_of_lower_half_block() {
+    let tile_set = TileSet::cga_ascii();
+    let blocks = BlockGlyphs::find(&tile_set, BlockResolution::Quadrants).unwrap();
+    let mut g = Graphic::solid(1, 1, blocks.tile(0b1100, RED, BLACK));
+    g.plot(&blocks, 1, 1, GREEN);
+    assert_eq!(pixels(&g, &blocks), vec![Some(BLACK), Some(BLACK), Some(GREEN), Some(GREEN)]);
+}